@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX meeting_talks;
+ALTER TABLE talks DROP COLUMN meeting;
\ No newline at end of file
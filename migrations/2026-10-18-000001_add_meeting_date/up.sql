@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE talks ADD COLUMN meeting DATE;
+
+CREATE INDEX meeting_talks ON talks (meeting);
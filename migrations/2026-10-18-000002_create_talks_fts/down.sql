@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER talks_fts_update;
+DROP TRIGGER talks_fts_delete;
+DROP TRIGGER talks_fts_insert;
+DROP TABLE talks_fts;
\ No newline at end of file
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE VIRTUAL TABLE talks_fts USING fts5(
+    name,
+    description,
+    content='talks',
+    content_rowid='id'
+);
+
+INSERT INTO talks_fts (talks_fts) VALUES ('rebuild');
+
+CREATE TRIGGER talks_fts_insert AFTER INSERT ON talks BEGIN
+    INSERT INTO talks_fts (rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
+
+CREATE TRIGGER talks_fts_delete AFTER DELETE ON talks BEGIN
+    INSERT INTO talks_fts (talks_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+END;
+
+CREATE TRIGGER talks_fts_update AFTER UPDATE OF name, description ON talks BEGIN
+    INSERT INTO talks_fts (talks_fts, rowid, name, description) VALUES ('delete', old.id, old.name, old.description);
+    INSERT INTO talks_fts (rowid, name, description) VALUES (new.id, new.name, new.description);
+END;
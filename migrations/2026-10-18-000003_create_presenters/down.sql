@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX presenter_talks;
+ALTER TABLE talks DROP COLUMN presenter_id;
+DROP INDEX presenter_names;
+DROP TABLE presenters;
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE presenters (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX presenter_names ON presenters (name COLLATE NOCASE);
+
+-- One presenter for every distinct name, ignoring case and surrounding spaces
+INSERT INTO presenters (name)
+    SELECT MIN(TRIM(name)) FROM talks GROUP BY LOWER(TRIM(name));
+
+ALTER TABLE talks ADD COLUMN presenter_id INTEGER REFERENCES presenters (id);
+
+UPDATE talks SET presenter_id = (
+    SELECT presenters.id FROM presenters WHERE presenters.name = TRIM(talks.name) COLLATE NOCASE
+);
+
+CREATE INDEX presenter_talks ON talks (presenter_id);
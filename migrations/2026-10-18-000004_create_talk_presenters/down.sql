@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE talks ADD COLUMN presenter_id INTEGER REFERENCES presenters (id);
+
+UPDATE talks SET presenter_id = (
+    SELECT presenter_id FROM talk_presenters
+    WHERE talk_presenters.talk_id = talks.id ORDER BY position LIMIT 1
+);
+
+CREATE INDEX presenter_talks ON talks (presenter_id);
+
+DROP INDEX presenter_talk_presenters;
+DROP TABLE talk_presenters;
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE talk_presenters (
+    talk_id INTEGER NOT NULL REFERENCES talks (id),
+    presenter_id INTEGER NOT NULL REFERENCES presenters (id),
+    -- Order the presenters are listed in, starting at 0
+    position INTEGER NOT NULL,
+    PRIMARY KEY (talk_id, presenter_id)
+);
+
+CREATE INDEX presenter_talk_presenters ON talk_presenters (presenter_id);
+
+INSERT INTO talk_presenters (talk_id, presenter_id, position)
+    SELECT id, presenter_id, 0 FROM talks WHERE presenter_id IS NOT NULL;
+
+DROP INDEX presenter_talks;
+ALTER TABLE talks DROP COLUMN presenter_id;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Minutes the talk is expected to take, NULL uses the default for its type
+ALTER TABLE talks ADD COLUMN duration INTEGER;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE talks DROP COLUMN presented_at;
+ALTER TABLE talks DROP COLUMN started_at;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- When the talk started and when it was done during the meeting
+ALTER TABLE talks ADD COLUMN started_at TIMESTAMP;
+ALTER TABLE talks ADD COLUMN presented_at TIMESTAMP;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE talks DROP COLUMN votes;
+DROP TABLE votes;
\ No newline at end of file
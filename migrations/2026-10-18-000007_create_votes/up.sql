@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Votes for a talk, each client may vote for a talk once
+CREATE TABLE votes (
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    client_id TEXT NOT NULL,
+    PRIMARY KEY (talk_id, client_id)
+);
+
+-- Number of votes, kept on the talk so talks can be ordered by it
+ALTER TABLE talks ADD COLUMN votes INTEGER NOT NULL DEFAULT 0;
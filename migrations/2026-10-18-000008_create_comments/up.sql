@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- Questions and follow-ups left on a talk, admins hide the ones that should not be shown
+CREATE TABLE comments (
+    id INTEGER PRIMARY KEY,
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    is_visible BOOLEAN DEFAULT TRUE NOT NULL
+);
+
+CREATE INDEX talk_comments ON comments (talk_id);
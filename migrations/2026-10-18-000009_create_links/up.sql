@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Slides, repositories and videos of a talk, in the order they were given
+CREATE TABLE links (
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    position INTEGER NOT NULL,
+    kind TINYINT NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (talk_id, position)
+);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Slide files uploaded for a talk, the file itself is stored on disk under its sha256
+CREATE TABLE attachments (
+    id INTEGER PRIMARY KEY,
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX talk_attachments ON attachments (talk_id);
+CREATE INDEX attachment_hashes ON attachments (sha256);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX talk_types_of_talks;
+DROP TABLE talk_types;
\ No newline at end of file
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+-- Talk types used to be a fixed enum stored as 0 to 4 in talks.talk_type, the
+-- same ids are kept so existing talks keep their type
+CREATE TABLE talk_types (
+    id INTEGER PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL UNIQUE,
+    heading TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    color TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    accepts_votes BOOLEAN NOT NULL DEFAULT FALSE,
+    after_meeting BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO talk_types (id, slug, label, heading, position, color, duration, accepts_votes, after_meeting) VALUES
+    (0, 'ForumTopic', 'forum topic', 'Forum Topics', 1, '#42a5f5', 10, TRUE, FALSE),
+    (1, 'LightningTalk', 'lightning talk', 'Lightning Talks', 2, '#ffca28', 5, FALSE, FALSE),
+    (2, 'ProjectUpdate', 'project update', 'Project Updates', 3, '#66bb6a', 3, FALSE, FALSE),
+    (3, 'Announcement', 'announcement', 'Announcements', 4, '#ab47bc', 2, FALSE, FALSE),
+    (4, 'AfterMeetingSlot', 'after meeting slot', 'After-Meeting Slots', 5, '#8d6e63', 15, FALSE, TRUE);
+
+CREATE INDEX talk_types_of_talks ON talks (talk_type);
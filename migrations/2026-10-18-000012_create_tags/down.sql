@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tags_by_tag;
+DROP TABLE tags;
\ No newline at end of file
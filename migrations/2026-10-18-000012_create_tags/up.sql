@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Topics of a talk, e.g. "rust" or "hardware", independent of its type
+CREATE TABLE tags (
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (talk_id, tag)
+);
+
+CREATE INDEX tags_by_tag ON tags(tag);
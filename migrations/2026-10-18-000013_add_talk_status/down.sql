@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX talks_by_status;
+
+ALTER TABLE talks ADD COLUMN is_visible BOOLEAN DEFAULT TRUE NOT NULL;
+UPDATE talks SET is_visible = meeting IS NULL AND status IN (1, 2, 3);
+CREATE INDEX visible_talks ON talks (is_visible);
+
+ALTER TABLE talks DROP COLUMN status;
\ No newline at end of file
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- Where a talk is in its lifecycle:
+-- 0 proposed, 1 scheduled, 2 presenting, 3 presented, 4 withdrawn, 5 rejected
+ALTER TABLE talks ADD COLUMN status TINYINT NOT NULL DEFAULT 1;
+
+-- Hidden talks were archived after the meeting they were given at
+UPDATE talks SET status = CASE
+    WHEN NOT is_visible THEN 3
+    WHEN presented_at IS NOT NULL THEN 3
+    WHEN started_at IS NOT NULL THEN 2
+    ELSE 1
+END;
+
+DROP INDEX visible_talks;
+ALTER TABLE talks DROP COLUMN is_visible;
+
+CREATE INDEX talks_by_status ON talks (status);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE talks DROP COLUMN submitted_by;
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- The client that proposed the talk, told when moderators reject it
+ALTER TABLE talks ADD COLUMN submitted_by TEXT;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE talks DROP COLUMN hidden_at;
+ALTER TABLE talks DROP COLUMN updated_at;
+ALTER TABLE talks DROP COLUMN created_at;
\ No newline at end of file
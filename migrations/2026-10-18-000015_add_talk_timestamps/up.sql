@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE talks ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE talks ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE talks ADD COLUMN hidden_at TIMESTAMP;
+
+-- Older talks only tell when they were presented and archived, the closest
+-- guess there is for when they were added, changed and hidden
+UPDATE talks SET
+    created_at = COALESCE(started_at, presented_at, meeting || ' 00:00:00', datetime('now', 'localtime')),
+    updated_at = COALESCE(presented_at, started_at, meeting || ' 00:00:00', datetime('now', 'localtime')),
+    hidden_at = CASE WHEN meeting IS NOT NULL THEN COALESCE(presented_at, meeting || ' 00:00:00') END;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- Every earlier version of a talk, saved when the talk is edited or reverted.
+-- Presenter names, links and tags are kept as json lists.
+CREATE TABLE talk_revisions (
+    id INTEGER PRIMARY KEY,
+    talk_id INTEGER NOT NULL REFERENCES talks(id),
+    name TEXT NOT NULL,
+    talk_type INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    duration INTEGER,
+    presenters TEXT NOT NULL,
+    links TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    replaced_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX talk_revisions_by_talk ON talk_revisions (talk_id);
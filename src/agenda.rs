@@ -0,0 +1,94 @@
+use chrono::{Duration, NaiveDate, NaiveTime};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{config::Config, model::{Talk, group_by_type}};
+
+// A talk and when it is projected to happen
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct AgendaItem {
+    pub talk: Talk,
+    pub minutes: i32,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+// The visible talks laid out over a meeting
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct Agenda {
+    pub date: NaiveDate,
+    pub start: NaiveTime,
+    // When the meeting is supposed to end
+    pub scheduled_end: NaiveTime,
+    // When the talks are projected to end, after-meeting slots are not counted
+    pub projected_end: NaiveTime,
+    pub minutes: i64,
+    pub meeting_length: i64,
+    // Minutes the talks run past the scheduled end, 0 when they fit
+    pub overrun: i64,
+    // Talks in meeting order, after-meeting slots start once the meeting ends
+    pub items: Vec<AgendaItem>,
+    // The talk being presented and the one that will follow it
+    pub current: Option<i32>,
+    pub next: Option<i32>,
+}
+
+impl Agenda {
+    pub fn is_over(&self) -> bool {
+        self.overrun > 0
+    }
+
+    pub fn current_item(&self) -> Option<&AgendaItem> {
+        self.items.iter().find(|item| Some(item.talk.id) == self.current)
+    }
+
+    pub fn next_item(&self) -> Option<&AgendaItem> {
+        self.items.iter().find(|item| Some(item.talk.id) == self.next)
+    }
+}
+
+// Lays out talks one after another from the start of the meeting
+pub fn plan(config: &Config, date: NaiveDate, talks: Vec<Talk>) -> Agenda {
+    let start = config.meeting_start;
+    let scheduled_end = start + Duration::minutes(config.meeting_length);
+
+    let mut items = Vec::new();
+    let mut minutes: i64 = 0;
+    for group in group_by_type(talks) {
+        for talk in group.talks {
+            let length = talk.minutes();
+
+            // After-meeting slots take place once the talks are over
+            if !talk.talk_type.after_meeting {
+                minutes += i64::from(length);
+            }
+
+            let previous_end = items.last().map_or(start, |item: &AgendaItem| item.end);
+            items.push(AgendaItem {
+                talk,
+                minutes: length,
+                start: previous_end,
+                end: previous_end + Duration::minutes(i64::from(length)),
+            });
+        }
+    }
+
+    // Same order as DBManager::present_next
+    let current = items.iter().find(|item| item.talk.is_current()).map(|item| item.talk.id);
+    let next = items.iter()
+        .find(|item| item.talk.presented_at.is_none() && Some(item.talk.id) != current)
+        .map(|item| item.talk.id);
+
+    Agenda {
+        date,
+        start,
+        scheduled_end,
+        projected_end: start + Duration::minutes(minutes),
+        minutes,
+        meeting_length: config.meeting_length,
+        overrun: (minutes - config.meeting_length).max(0),
+        items,
+        current,
+        next,
+    }
+}
@@ -1,29 +1,127 @@
-use std::net::IpAddr;
+use std::{fs, net::IpAddr};
 use askama::Template;
+use chrono::{Local, NaiveDate};
+use futures::TryStreamExt;
 use uuid::Uuid;
-use warp::{Rejection, Reply, hyper::StatusCode, reply::{html, json}};
-use serde::{Serialize, Deserialize};
+use warp::{Rejection, Reply, hyper::{StatusCode, body::Buf}, multipart::{FormData, Part}, reply::{html, json, with_status}};
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize, Deserializer, de::{IntoDeserializer, DeserializeOwned}};
 
-use crate::{Clients, client::{Client, client_connection}, db::DBManager, model::Talk};
+use crate::{Clients, agenda::{self, Agenda}, attachments, client::{Client, client_connection}, config::SharedConfig, db::DBManager, error::{AppError, ErrorType}, events::{EventRequest, EventResponse, listed_talk, process_event, send_events}, minutes::render_minutes, openapi::{event_schemas, openapi}, presenters::suggest, revisions::{self, RevisionChanges}, stats::{self, Count, Stats, bar_chart}, tags::{self, CloudTag, tidy_tag}, timer::SharedTimer, model::{Attachment, Comment, Link, Meeting, PresenterHistory, PresenterSummary, SearchResult, TaggedTalks, Talk, TalkDetail, TalkGroup, TalkQuery, TalkStatus, TalkType, group_by_type}};
 
 #[derive(Template)]
 #[template(path = "index.j2")]
 struct IndexTemplate {
-    talks: Vec<Talk>
+    talks: Vec<Talk>,
+    talk_types: Vec<TalkType>,
+    agenda: Agenda,
+    // Forum topics are listed by votes, new votes move them
+    sort_by_votes: bool,
+}
+
+#[derive(Template)]
+#[template(path = "present.j2")]
+struct PresentTemplate {
+    agenda: Agenda
 }
 
 #[derive(Template)]
 #[template(path = "all.j2")]
 struct AllTemplate {
-    talks: Vec<Talk>
+    talks: Vec<Talk>,
+    next_cursor: Option<String>
+}
+
+#[derive(Template)]
+#[template(path = "meetings.j2")]
+struct MeetingsTemplate {
+    meetings: Vec<Meeting>
+}
+
+#[derive(Template)]
+#[template(path = "meeting.j2")]
+struct MeetingTemplate {
+    date: NaiveDate,
+    previous: Option<NaiveDate>,
+    next: Option<NaiveDate>,
+    groups: Vec<TalkGroup>
+}
+
+#[derive(Template)]
+#[template(path = "search.j2")]
+struct SearchTemplate {
+    q: String,
+    tag: String,
+    from: String,
+    to: String,
+    types: Vec<TypeOption>,
+    results: Vec<SearchResult>
+}
+
+#[derive(Template)]
+#[template(path = "stats.j2")]
+struct StatsTemplate {
+    type_chart: String,
+    presenter_chart: String,
+    semester_chart: String,
+    stats: Stats
+}
+
+#[derive(Template)]
+#[template(path = "presenters.j2")]
+struct PresentersTemplate {
+    presenters: Vec<PresenterSummary>
+}
+
+#[derive(Template)]
+#[template(path = "talk.j2")]
+struct TalkTemplate {
+    talk: Talk,
+    attachments: Vec<Attachment>,
+    comments: Vec<Comment>,
+}
+
+#[derive(Template)]
+#[template(path = "history.j2")]
+struct HistoryTemplate {
+    talk: Talk,
+    history: Vec<RevisionChanges>,
+}
+
+#[derive(Template)]
+#[template(path = "presenter.j2")]
+struct PresenterTemplate {
+    history: PresenterHistory
+}
+
+#[derive(Template)]
+#[template(path = "tags.j2")]
+struct TagsTemplate {
+    tags: Vec<CloudTag>
+}
+
+#[derive(Template)]
+#[template(path = "tag.j2")]
+struct TagTemplate {
+    tagged: TaggedTalks
+}
+
+struct TypeOption {
+    value: String,
+    label: String,
+    selected: bool
 }
 
 // Return the talks homepage
-pub async fn welcome_handler(db: DBManager) -> Result<impl Reply, Rejection> {
-    match db.list_visible_talks() {
+pub async fn welcome_handler(config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_visible_talks(config.sort_by_votes) {
         Ok(talks) => { 
+            let today = Local::now().date_naive();
             let template = IndexTemplate {
-                talks
+                agenda: agenda::plan(&config, today, talks.clone()),
+                talks,
+                talk_types: db.list_talk_types()?,
+                sort_by_votes: config.sort_by_votes,
             };
 
             Ok(html(template.render().unwrap()))
@@ -32,11 +130,37 @@ pub async fn welcome_handler(db: DBManager) -> Result<impl Reply, Rejection> {
     }
 }
 
-pub async fn all_talks_handler(db: DBManager) -> Result<impl Reply, Rejection> {
-    match db.list_all_talks() {
-        Ok(talks) => { 
+#[derive(Deserialize, Debug)]
+pub struct PageQuery {
+    cursor: Option<String>,
+}
+
+// Every talk ever created in chronological order, one page at a time
+pub async fn all_talks_handler(page: PageQuery, db: DBManager) -> Result<impl Reply, Rejection> {
+    let query = TalkQuery {
+        cursor: page.cursor,
+        ..TalkQuery::default()
+    };
+
+    match db.list_talks(&query) {
+        Ok(page) => {
             let template = AllTemplate {
-                talks
+                talks: page.talks,
+                next_cursor: page.next_cursor
+            };
+
+            Ok(html(template.render().unwrap()))
+        }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Lists the past meetings
+pub async fn meetings_handler(db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_meetings() {
+        Ok(meetings) => {
+            let template = MeetingsTemplate {
+                meetings
             };
 
             Ok(html(template.render().unwrap()))
@@ -45,6 +169,275 @@ pub async fn all_talks_handler(db: DBManager) -> Result<impl Reply, Rejection> {
     }
 }
 
+// Shows the talks presented at a past meeting
+pub async fn meeting_handler(date: String, db: DBManager) -> Result<impl Reply, Rejection> {
+    let date = parse_meeting_date(&date)?;
+
+    let talks = db.list_meeting_talks(date)?;
+    if talks.is_empty() {
+        return Err(AppError::new(&format!("no meeting on {}", date), ErrorType::NotFound).into());
+    }
+
+    let template = MeetingTemplate {
+        date,
+        previous: db.previous_meeting(date)?,
+        next: db.next_meeting(date)?,
+        groups: group_by_type(talks),
+    };
+
+    Ok(html(template.render().unwrap()))
+}
+
+fn parse_meeting_date(date: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| AppError::new(&format!("invalid meeting {}", date), ErrorType::NotFound))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default, rename = "type", deserialize_with = "empty_as_none")]
+    talk_type: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    tag: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    from: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    to: Option<NaiveDate>,
+}
+
+// Html forms send empty strings for fields that were left blank
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where D: Deserializer<'de>, T: DeserializeOwned {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.is_empty() => {
+            T::deserialize(value.into_deserializer()).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+// Whether the client asked for a web page rather than json
+fn wants_html(accept: &Option<String>) -> bool {
+    accept.as_ref().is_some_and(|accept| accept.contains("text/html"))
+}
+
+// Full-text search over talks, renders the search page for browsers and json otherwise
+pub async fn search_handler(query: SearchQuery, accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let kind = match &query.talk_type {
+        Some(name) => Some(db.find_talk_type(name)?),
+        None => None,
+    };
+    let results = db.search_talks(&query.q, kind.as_ref().map(|kind| kind.id), query.tag.as_deref(), query.from, query.to)?;
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&results)));
+    }
+
+    let template = SearchTemplate {
+        types: db.list_talk_types()?.into_iter()
+            .map(|talk_type| TypeOption {
+                selected: kind.as_ref().is_some_and(|kind| kind.id == talk_type.id),
+                value: talk_type.slug,
+                label: talk_type.label,
+            })
+            .collect(),
+        q: query.q,
+        tag: query.tag.unwrap_or_default(),
+        from: query.from.map(|date| date.to_string()).unwrap_or_default(),
+        to: query.to.map(|date| date.to_string()).unwrap_or_default(),
+        results,
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Statistics over every talk, renders charts for browsers and json otherwise
+pub async fn stats_handler(accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let stats = stats::collect(&db)?;
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&stats)));
+    }
+
+    let semesters: Vec<Count> = stats.by_semester.iter()
+        .map(|semester| Count { label: semester.semester.clone(), talks: semester.talks })
+        .collect();
+
+    let template = StatsTemplate {
+        type_chart: bar_chart(&stats.by_type),
+        presenter_chart: bar_chart(&stats.by_presenter),
+        semester_chart: bar_chart(&semesters),
+        stats,
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Lists every presenter, renders the presenters page for browsers and json otherwise
+pub async fn presenters_handler(accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let presenters = db.list_presenters()?;
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&presenters)));
+    }
+
+    let template = PresentersTemplate {
+        presenters
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Every talk given by a presenter
+pub async fn presenter_handler(id: i32, accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let history = PresenterHistory {
+        presenter: db.get_presenter(id)?,
+        talks: db.list_presenter_talks(id)?,
+    };
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&history)));
+    }
+
+    let template = PresenterTemplate {
+        history
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Every tag in use, renders the tag cloud for browsers and json otherwise
+pub async fn tags_handler(accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let tags = db.list_tags()?;
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&tags)));
+    }
+
+    let template = TagsTemplate {
+        tags: tags::cloud(tags)
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Every talk with a tag
+pub async fn tag_handler(tag: String, accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let tag = tidy_tag(&tag);
+    let tagged = TaggedTalks {
+        talks: db.list_tag_talks(&tag)?,
+        tag,
+    };
+
+    if tagged.talks.is_empty() {
+        return Err(AppError::new(&format!("no talks tagged {}", tagged.tag), ErrorType::NotFound).into());
+    }
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&tagged)));
+    }
+
+    let template = TagTemplate {
+        tagged
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// A talk with its attachments and comments, renders the talk page for browsers and json otherwise
+pub async fn talk_handler(id: i32, accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let talk = db.get_talk(id)?;
+    if !talk.status.is_listed() {
+        return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound).into());
+    }
+
+    let detail = TalkDetail {
+        talk,
+        attachments: db.list_attachments(id)?,
+        comments: db.list_comments(id)?,
+    };
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&detail)));
+    }
+
+    let template = TalkTemplate {
+        talk: detail.talk,
+        attachments: detail.attachments,
+        comments: detail.comments,
+    };
+
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+// Every earlier version of a talk with the fields each edit changed, newest first
+fn talk_history(id: i32, db: &DBManager) -> Result<(Talk, Vec<RevisionChanges>), AppError> {
+    let talk = db.get_talk(id)?;
+    if !talk.status.is_listed() {
+        return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound));
+    }
+
+    let history = revisions::history(&talk, db.list_revisions(id)?, &db.list_talk_types()?);
+    Ok((talk, history))
+}
+
+// The revision history of a talk, renders the history page for browsers and json otherwise
+pub async fn history_handler(id: i32, accept: Option<String>, db: DBManager) -> Result<Box<dyn Reply>, Rejection> {
+    let (talk, history) = talk_history(id, &db)?;
+
+    if !wants_html(&accept) {
+        return Ok(Box::new(json(&history)));
+    }
+
+    let template = HistoryTemplate { talk, history };
+    Ok(Box::new(html(template.render().unwrap())))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SuggestQuery {
+    #[serde(default)]
+    name: String,
+}
+
+// Existing presenters with names similar to the one being typed
+pub async fn suggest_presenters_handler(query: SuggestQuery, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_presenters() {
+        Ok(presenters) => { Ok(json(&suggest(&query.name, presenters))) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Returns the meeting minutes for the currently visible talks
+pub async fn minutes_handler(config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_visible_talks(config.sort_by_votes) {
+        Ok(talks) => {
+            let today = Local::now().date_naive();
+            Ok(markdown(render_minutes(&config.minutes_template, today, talks)))
+        }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Returns the meeting minutes for the talks archived at a past meeting
+pub async fn meeting_minutes_handler(file: String, config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    let date = match file.strip_suffix(".md") {
+        Some(date) => parse_meeting_date(date)?,
+        None => return Err(AppError::new(&format!("invalid meeting {}", file), ErrorType::NotFound).into()),
+    };
+
+    let talks = db.list_meeting_talks(date)?;
+    if talks.is_empty() {
+        return Err(AppError::new(&format!("no meeting on {}", date), ErrorType::NotFound).into());
+    }
+
+    Ok(markdown(render_minutes(&config.minutes_template, date, talks)))
+}
+
+fn markdown(body: String) -> impl Reply {
+    warp::reply::with_header(body, "content-type", "text/markdown; charset=utf-8")
+}
+
 // Always returns 200
 pub async fn health_handler(clients: Clients) -> Result<impl Reply, Rejection>  {
     let clients = clients.read().await;
@@ -52,7 +445,7 @@ pub async fn health_handler(clients: Clients) -> Result<impl Reply, Rejection>
     Ok(format!("Open Connections: {}\n", clients.len()))
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, JsonSchema)]
 pub struct RegisterResponse {
     id: String,
     authenticated: bool,
@@ -83,8 +476,11 @@ pub async fn register_handler(addr: Option<IpAddr>, clients: Clients) -> Result<
     clients.write().await.insert(
         id.clone(),
         Client {
+            id: id.clone(),
             sender: None,
             authenticated,
+            admin: false,
+            watching: None,
             second_chance: true,
         }
     );
@@ -96,53 +492,526 @@ pub async fn register_handler(addr: Option<IpAddr>, clients: Clients) -> Result<
     }))
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, JsonSchema)]
 pub struct AuthenticateRequest {
     id: String,
     password: String,
 }
 
-pub async fn authenticate(request: AuthenticateRequest, clients: Clients) -> Result<impl Reply, Rejection> {
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct AuthenticateResponse {
+    authenticated: bool,
+    // Admins may merge presenters and moderate the meeting
+    admin: bool,
+}
+
+pub async fn authenticate(request: AuthenticateRequest, config: SharedConfig, clients: Clients) -> Result<impl Reply, Rejection> {
+    let admin = config.admin_password.as_ref().is_some_and(|password| *password == request.password);
+
     // Check if the client is already authenticated
-    if request.password == "conway" {
+    if admin || request.password == "conway" {
         let mut writer = clients.write().await;
         match writer.get_mut(&request.id) {
             Some(client) => {
                 // set authenticated flag
                 client.authenticated = true;
-                return Ok(StatusCode::OK);
+                client.admin |= admin;
+
+                let response = AuthenticateResponse { authenticated: true, admin: client.admin };
+                return Ok(with_status(json(&response), StatusCode::OK));
             }
             None => {
-                return Ok(StatusCode::BAD_REQUEST);
+                let response = ErrorResponse { error: format!("unknown client {}", request.id) };
+                return Ok(with_status(json(&response), StatusCode::BAD_REQUEST));
             }
         }
     }
 
-    return Ok(StatusCode::FORBIDDEN);
+    let response = ErrorResponse { error: String::from("wrong password") };
+    return Ok(with_status(json(&response), StatusCode::FORBIDDEN));
 }
 
-pub async fn visible_talks(db: DBManager) -> Result<impl Reply, Rejection> {
-    match db.list_visible_talks() {
-        Ok(talks) => { 
-            let mut str = talks.iter()
-                .filter_map(|talk| serde_json::to_string(talk).ok())
-                .fold(String::from("["), |a, b| a + &b + ",");            
-            
-            // remove the last `,`
-            str.pop();
-            str.push(']');
-
-            Ok(str)
+pub async fn visible_talks(config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_visible_talks(config.sort_by_votes) {
+        Ok(talks) => { Ok(json(&talks)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Read-only view of the running order for the projector
+pub async fn present_handler(config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_visible_talks(config.sort_by_votes) {
+        Ok(talks) => {
+            let today = Local::now().date_naive();
+            let template = PresentTemplate {
+                agenda: agenda::plan(&config, today, talks)
+            };
+
+            Ok(html(template.render().unwrap()))
+        }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// The visible talks laid out over today's meeting
+pub async fn agenda_handler(config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_visible_talks(config.sort_by_votes) {
+        Ok(talks) => {
+            let today = Local::now().date_naive();
+            Ok(json(&agenda::plan(&config, today, talks)))
         }
         Err(err) => { Err(err.into()) }
     }
 }
 
+// Lists talks as json, filtered and paged by the query parameters
+pub async fn list_talks_handler(query: TalkQuery, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_talks(&query) {
+        Ok(page) => { Ok(json(&page)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct CreateTalkRequest {
+    // Either name a single presenter or list every presenter
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    presenters: Vec<String>,
+    // Slug or label of a talk type
+    talk_type: String,
+    description: String,
+    duration: Option<i32>,
+    #[serde(default)]
+    links: Vec<Link>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct UpdateTalkRequest {
+    name: Option<String>,
+    presenters: Option<Vec<String>>,
+    talk_type: Option<String>,
+    description: Option<String>,
+    duration: Option<i32>,
+    links: Option<Vec<Link>>,
+    tags: Option<Vec<String>>,
+}
+
+// Api requests identify themselves with the id returned by /register
+async fn requesting_client(id: Option<String>, clients: &Clients) -> Client {
+    let client = match id {
+        Some(id) => clients.read().await.get(&id).cloned(),
+        None => None,
+    };
+
+    client.unwrap_or(Client {
+        id: String::new(),
+        sender: None,
+        authenticated: false,
+        admin: false,
+        watching: None,
+        second_chance: false,
+    })
+}
+
+// Proposed and rejected talks are only shown to moderators
+pub async fn get_talk_handler(id: i32, db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.get_talk(id) {
+        Ok(talk) if talk.status.is_listed() => { Ok(json(&talk)) }
+        Ok(_) => { Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound).into()) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+pub async fn create_talk_handler(client_id: Option<String>, request: CreateTalkRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    // Processed exactly like the websocket event and broadcast to every client
+    let event = EventRequest::Create { name: request.name, presenters: request.presenters, talk_type: request.talk_type, desc: request.description, duration: request.duration, links: request.links, tags: request.tags };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response.clone()).await;
+
+    match response.talk_id().map(|id| db.get_talk(id)) {
+        Some(Ok(talk)) => { Ok(with_status(json(&talk), StatusCode::CREATED)) }
+        Some(Err(err)) => { Err(err.into()) }
+        None => { Err(AppError::new("talk was not created", ErrorType::Internal).into()) }
+    }
+}
+
+pub async fn update_talk_handler(id: i32, client_id: Option<String>, request: UpdateTalkRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let event = EventRequest::Update { id, name: request.name, presenters: request.presenters, talk_type: request.talk_type, desc: request.description, duration: request.duration, links: request.links, tags: request.tags };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Deleting a talk hides it, archiving it under today's meeting like the Hide event
+pub async fn delete_talk_handler(id: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::Hide { id }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The status a talk moves to, see TalkStatus::can_become for the allowed steps
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct SetStatusRequest {
+    status: TalkStatus,
+}
+
+// Anyone may withdraw a talk, only admins may schedule or reject one
+pub async fn set_status_handler(id: i32, client_id: Option<String>, request: SetStatusRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::SetStatus { id, status: request.status }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+pub async fn revisions_handler(id: i32, db: DBManager) -> Result<impl Reply, Rejection> {
+    let (_, history) = talk_history(id, &db)?;
+    Ok(json(&history))
+}
+
+// Only admins may revert talks, the reverted talk is broadcast as an Update event
+pub async fn revert_handler(id: i32, revision: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::Revert { id, revision }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Talks proposed by visitors, only admins see them before they are approved
+pub async fn proposals_handler(client_id: Option<String>, clients: Clients, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+    if !client.admin {
+        return Err(AppError::new("only admins may list proposed talks", ErrorType::Forbidden).into());
+    }
+
+    match db.list_proposals() {
+        Ok(talks) => { Ok(json(&talks)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Approving schedules the talk and shows it to every client
+pub async fn approve_handler(id: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::Approve { id }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RejectRequest {
+    reason: Option<String>,
+}
+
+// The visitor who proposed the talk is told why, when they are still connected
+pub async fn reject_handler(id: i32, client_id: Option<String>, request: RejectRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::Reject { id, reason: request.reason }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Votes for a forum topic, each client may vote for a topic once
+pub async fn vote_handler(id: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::Vote { id }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk(id) {
+        Ok(talk) => { Ok(json(&talk)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Only admins see the comments of proposed and rejected talks
+pub async fn comments_handler(id: i32, client_id: Option<String>, clients: Clients, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+    listed_talk(&db, &client, id)?;
+
+    match db.list_comments(id) {
+        Ok(comments) => { Ok(json(&comments)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct CreateCommentRequest {
+    author: String,
+    body: String,
+}
+
+// Comments are sent to the clients watching the talk
+pub async fn create_comment_handler(id: i32, client_id: Option<String>, request: CreateCommentRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let event = EventRequest::Comment { talk_id: id, author: request.author, body: request.body };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response.clone()).await;
+
+    match response {
+        EventResponse::Comment { id, .. } => { Ok(with_status(json(&db.get_comment(id)?), StatusCode::CREATED)) }
+        _ => { Err(AppError::new("comment was not created", ErrorType::Internal).into()) }
+    }
+}
+
+// Only admins may hide comments
+pub async fn hide_comment_handler(id: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::HideComment { id }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Only admins see the slide files of proposed and rejected talks
+pub async fn attachments_handler(id: i32, client_id: Option<String>, clients: Clients, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+    listed_talk(&db, &client, id)?;
+
+    match db.list_attachments(id) {
+        Ok(attachments) => { Ok(json(&attachments)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+// Uploads the "file" field of a multipart form as a slide file of the talk
+pub async fn upload_attachment_handler(id: i32, client_id: Option<String>, form: FormData, clients: Clients, config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+    if !client.authenticated {
+        return Err(AppError::new("client is not authenticated", ErrorType::Forbidden).into());
+    }
+    listed_talk(&db, &client, id)?;
+
+    let parts: Vec<Part> = form.try_collect().await.map_err(|err| {
+        AppError::new(&format!("invalid upload: {}", err), ErrorType::BadRequest)
+    })?;
+    let mut file = match parts.into_iter().find(|part| part.name() == "file") {
+        Some(file) => file,
+        None => return Err(AppError::new("the upload needs a file field", ErrorType::BadRequest).into()),
+    };
+
+    let file_name = file.filename().unwrap_or_default().to_string();
+    let mut data = Vec::new();
+    while let Some(chunk) = file.data().await {
+        let chunk = chunk.map_err(|err| AppError::new(&format!("invalid upload: {}", err), ErrorType::BadRequest))?;
+        data.extend_from_slice(chunk.chunk());
+    }
+
+    let (attachment, created) = attachments::attach(&db, &config, id, &file_name, &data)?;
+    if !created {
+        return Ok(with_status(json(&attachment), StatusCode::OK));
+    }
+
+    let event = EventResponse::Attachment {
+        id: attachment.id,
+        talk_id: attachment.talk_id,
+        file_name: attachment.file_name.clone(),
+        content_type: attachment.content_type.clone(),
+        size: attachment.size,
+    };
+    send_events(clients, event).await;
+
+    Ok(with_status(json(&attachment), StatusCode::CREATED))
+}
+
+// Downloads an attachment under the name it was uploaded with
+pub async fn download_attachment_handler(id: i32, config: SharedConfig, db: DBManager) -> Result<impl Reply, Rejection> {
+    let attachment = db.get_attachment(id)?;
+    let data = fs::read(attachments::path(&config.attachments_dir, &attachment.sha256)).map_err(|err| {
+        AppError::new(&format!("reading attachment {}: {}", id, err), ErrorType::NotFound)
+    })?;
+
+    let reply = warp::reply::with_header(data, "content-type", attachment.content_type);
+    let reply = warp::reply::with_header(reply, "content-disposition", attachments::content_disposition(&attachment.file_name));
+    Ok(warp::reply::with_header(reply, "x-content-type-options", "nosniff"))
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct MergePresentersRequest {
+    from: i32,
+    into: i32,
+}
+
+// Moves every talk of one presenter to another, only admins may merge presenters
+pub async fn merge_presenters_handler(client_id: Option<String>, request: MergePresentersRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let event = EventRequest::MergePresenters { from: request.from, into: request.into };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    let history = PresenterHistory {
+        presenter: db.get_presenter(request.into)?,
+        talks: db.list_presenter_talks(request.into)?,
+    };
+
+    Ok(json(&history))
+}
+
+// Every talk type in the order talks are presented
+pub async fn talk_types_handler(db: DBManager) -> Result<impl Reply, Rejection> {
+    match db.list_talk_types() {
+        Ok(talk_types) => { Ok(json(&talk_types)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct CreateTalkTypeRequest {
+    slug: String,
+    label: String,
+    heading: Option<String>,
+    position: Option<i32>,
+    color: Option<String>,
+    duration: i32,
+    #[serde(default)]
+    accepts_votes: bool,
+    #[serde(default)]
+    after_meeting: bool,
+}
+
+// Only admins may manage talk types, every change is broadcast as a TalkTypes event
+pub async fn create_talk_type_handler(client_id: Option<String>, request: CreateTalkTypeRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let slug = request.slug.clone();
+    let event = EventRequest::CreateTalkType {
+        slug: request.slug,
+        label: request.label,
+        heading: request.heading,
+        position: request.position,
+        color: request.color,
+        duration: request.duration,
+        accepts_votes: request.accepts_votes,
+        after_meeting: request.after_meeting,
+    };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    Ok(with_status(json(&db.find_talk_type(&slug)?), StatusCode::CREATED))
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct UpdateTalkTypeRequest {
+    slug: Option<String>,
+    label: Option<String>,
+    heading: Option<String>,
+    position: Option<i32>,
+    color: Option<String>,
+    duration: Option<i32>,
+    accepts_votes: Option<bool>,
+    after_meeting: Option<bool>,
+}
+
+pub async fn update_talk_type_handler(id: i32, client_id: Option<String>, request: UpdateTalkTypeRequest, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let event = EventRequest::UpdateTalkType {
+        id,
+        slug: request.slug,
+        label: request.label,
+        heading: request.heading,
+        position: request.position,
+        color: request.color,
+        duration: request.duration,
+        accepts_votes: request.accepts_votes,
+        after_meeting: request.after_meeting,
+    };
+    let response = process_event(event, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    match db.get_talk_type(id) {
+        Ok(talk_type) => { Ok(json(&talk_type)) }
+        Err(err) => { Err(err.into()) }
+    }
+}
+
+pub async fn delete_talk_type_handler(id: i32, client_id: Option<String>, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
+    let client = requesting_client(client_id, &clients).await;
+
+    let response = process_event(EventRequest::DeleteTalkType { id }, &db, &client, &config, &timer)?;
+    send_events(clients, response).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Time left for the talk being presented, Timer events only arrive while it runs
+pub async fn timer_handler(timer: SharedTimer) -> Result<impl Reply, Rejection> {
+    let state = timer.lock().unwrap().state();
+    Ok(json(&state))
+}
+
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct ErrorResponse {
+    error: String,
+}
+
+// Turns application errors into a json body with a matching status code
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    match err.find::<AppError>() {
+        Some(err) => {
+            let body = ErrorResponse { error: err.message.clone() };
+            Ok(with_status(json(&body), err.to_http_status()))
+        }
+        None => { Err(err) }
+    }
+}
+
+pub async fn openapi_handler() -> Result<impl Reply, Rejection> {
+    Ok(json(&openapi()))
+}
+
+// Json schema of a single websocket event, e.g. /api/schemas/request/Create.json
+pub async fn event_schema_handler(direction: String, file: String) -> Result<impl Reply, Rejection> {
+    let name = format!("{}/{}", direction, file);
+
+    match event_schemas().into_iter().find(|(schema, _)| *schema == name) {
+        Some((_, schema)) => { Ok(json(&schema)) }
+        None => { Err(AppError::new(&format!("no schema for {}", name), ErrorType::NotFound).into()) }
+    }
+}
+
 // Turns HTTP request into a websocket
-pub async fn ws_handler(ws: warp::ws::Ws, id: String, clients: Clients, db: DBManager) -> Result<impl Reply, Rejection> {
+pub async fn ws_handler(ws: warp::ws::Ws, id: String, clients: Clients, config: SharedConfig, timer: SharedTimer, db: DBManager) -> Result<impl Reply, Rejection> {
     let client = clients.read().await.get(&id).cloned();
     match client {
-        Some(c) => Ok(ws.on_upgrade(move |socket| client_connection(socket, id, clients, c, db))),
+        Some(c) => Ok(ws.on_upgrade(move |socket| client_connection(socket, id, clients, c, config, timer, db))),
         None => Err(warp::reject::not_found()),
     }
 }
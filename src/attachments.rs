@@ -0,0 +1,130 @@
+use std::{fmt::Write, fs, io, path::{Path, PathBuf}};
+
+use chrono::{Local, SubsecRound};
+use sha2::{Digest, Sha256};
+
+use crate::{config::Config, db::DBManager, error::{AppError, ErrorType}, model::{Attachment, CreateAttachment}};
+
+// Longest file name kept for an attachment, in characters
+const MAX_FILE_NAME: usize = 200;
+
+// First bytes of the slide formats that may be uploaded
+const PDF: &[u8] = b"%PDF-";
+const ZIP: &[u8] = b"PK\x03\x04";
+const OLE: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+
+// Content type of an uploaded slide file, None when it is not a slide format
+//
+// The extension picks the format and the contents have to match it, the
+// content type sent by the browser is not trusted.
+pub fn content_type(file_name: &str, data: &[u8]) -> Option<&'static str> {
+    let extension = Path::new(file_name).extension()?.to_str()?.to_lowercase();
+
+    match extension.as_str() {
+        "pdf" if data.starts_with(PDF) => Some("application/pdf"),
+        "odp" if data.starts_with(ZIP) => Some("application/vnd.oasis.opendocument.presentation"),
+        "pptx" if data.starts_with(ZIP) => Some("application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+        "ppt" if data.starts_with(OLE) => Some("application/vnd.ms-powerpoint"),
+        _ => None,
+    }
+}
+
+// File name without directories, quotes or control characters, None when nothing is left
+pub fn clean_file_name(file_name: &str) -> Option<String> {
+    let base = file_name.rsplit(['/', '\\']).next()?;
+    let cleaned: String = base.chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILE_NAME)
+        .collect();
+    let cleaned = cleaned.trim();
+
+    if cleaned.is_empty() || cleaned.starts_with('.') {
+        None
+    } else {
+        Some(cleaned.to_string())
+    }
+}
+
+pub fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Files are stored under their hash, identical uploads share one file
+pub fn path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join(sha256)
+}
+
+pub fn store(dir: &Path, sha256: &str, data: &[u8]) -> io::Result<()> {
+    let path = path(dir, sha256);
+    if path.exists() {
+        return Ok(());
+    }
+
+    // Write to a temporary file first so a failed upload never leaves half a file behind
+    fs::create_dir_all(dir)?;
+    let partial = dir.join(format!("{}.part", sha256));
+    fs::write(&partial, data)?;
+    fs::rename(&partial, &path)
+}
+
+// Header value that downloads the file under its original name
+pub fn content_disposition(file_name: &str) -> String {
+    // Browsers that ignore `filename*` get the name with anything but printable ascii replaced
+    let fallback: String = file_name.chars()
+        .map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '_' })
+        .collect();
+
+    let mut encoded = String::new();
+    for byte in file_name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+// Checks and stores an uploaded slide file, returns the attachment and whether it is new
+//
+// Uploading a file the talk already has returns the existing attachment.
+pub fn attach(db: &DBManager, config: &Config, talk_id: i32, file_name: &str, data: &[u8]) -> Result<(Attachment, bool), AppError> {
+    let size = data.len() as u64;
+    if size == 0 {
+        return Err(AppError::new("the uploaded file is empty", ErrorType::BadRequest));
+    }
+    if size > config.max_attachment_size {
+        return Err(AppError::new(&format!("files are limited to {} MB", config.max_attachment_size / 1024 / 1024), ErrorType::BadRequest));
+    }
+
+    let file_name = clean_file_name(file_name)
+        .ok_or_else(|| AppError::new("the uploaded file needs a name", ErrorType::BadRequest))?;
+    let content_type = content_type(&file_name, data)
+        .ok_or_else(|| AppError::new("only pdf, odp, pptx and ppt slides may be uploaded", ErrorType::BadRequest))?;
+
+    db.get_talk(talk_id)?;
+    let sha256 = sha256(data);
+    if let Some(attachment) = db.find_attachment(talk_id, &sha256)? {
+        return Ok((attachment, false));
+    }
+
+    let used = db.attachments_size(talk_id)? as u64;
+    if used + size > config.attachment_quota {
+        return Err(AppError::new(&format!("the files of a talk are limited to {} MB", config.attachment_quota / 1024 / 1024), ErrorType::BadRequest));
+    }
+
+    store(&config.attachments_dir, &sha256, data).map_err(|err| {
+        AppError::new(&format!("storing attachment of talk {}: {}", talk_id, err), ErrorType::Internal)
+    })?;
+
+    let attachment = CreateAttachment {
+        talk_id,
+        file_name: &file_name,
+        content_type,
+        size: size as i64,
+        sha256: &sha256,
+        created_at: Local::now().naive_local().trunc_subsecs(0),
+    };
+    Ok((db.create_attachment(attachment)?, true))
+}
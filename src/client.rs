@@ -4,7 +4,7 @@ use futures::{StreamExt, channel::mpsc::{self, UnboundedSender}};
 use tokio::sync::RwLock;
 use warp::ws::{Message, WebSocket};
 
-use crate::{db::DBManager, events::{EventRequest, process_event, send_events}};
+use crate::{config::SharedConfig, db::DBManager, error::{AppError, ErrorType}, events::{EventRequest, process_event, send_error, send_events}, timer::SharedTimer};
 
 // Clients type
 pub type Clients = Arc<RwLock<HashMap<String, Client>>>;
@@ -15,13 +15,19 @@ pub fn create_clients() -> Clients {
 
 #[derive(Debug, Clone)]
 pub struct Client {
+    // Id returned by /register
+    pub id: String,
     pub sender: Option<UnboundedSender<std::result::Result<Message, warp::Error>>>,
     pub authenticated: bool,
+    // Admins authenticated with ADMIN_PASSWORD and may merge presenters
+    pub admin: bool,
+    // Talk whose comments the client is sent
+    pub watching: Option<i32>,
     pub second_chance: bool
 }
 
 // Handles the connection to the websocket
-pub async fn client_connection(ws: WebSocket, id: String, clients: Clients, mut client: Client, db: DBManager) {
+pub async fn client_connection(ws: WebSocket, id: String, clients: Clients, mut client: Client, config: SharedConfig, timer: SharedTimer, db: DBManager) {
     let (client_ws_sender, mut client_ws_rcv) = ws.split();
     let (client_sender, client_rcv) = mpsc::unbounded();
 
@@ -36,24 +42,43 @@ pub async fn client_connection(ws: WebSocket, id: String, clients: Clients, mut
 
     // Red messages forever
     while let Some(result) = client_ws_rcv.next().await {
-        // Checks if the client is authenticated
-        if clients.read().await.get(&id).map_or(false, |client| client.authenticated) {
-            // Read message
-            let msg = match result {
-                Ok(msg) => msg,
-                Err(e) => {
-                    eprintln!("error receiving ws message for id: {}): {}", id.clone(), e);
-                    break;
+        // Read message
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(e) => {
+                eprintln!("error receiving ws message for id: {}): {}", id.clone(), e);
+                break;
+            }
+        };
+
+        // If message is a string
+        if let Ok(str) = msg.to_str() {
+            // Parse message as event
+            let event = match serde_json::from_str::<EventRequest>(str) {
+                Ok(event) => event,
+                Err(err) => {
+                    let err = AppError::new(&format!("invalid request: {}", err), ErrorType::BadRequest);
+                    send_error(&clients, &id, &err).await;
+                    continue;
                 }
             };
 
-            // If message is a string
-            if let Ok(str) = msg.to_str() {
-                // Parse message as event
-                if let Ok(event) = serde_json::from_str::<EventRequest>(&str) {
-                    let response = process_event(event, &db);
-                    send_events(clients.clone(), response).await;
+            // Anyone may watch a talk, the event is never broadcast
+            if let EventRequest::Watch { id: talk } = event {
+                if let Some(client) = clients.write().await.get_mut(&id) {
+                    client.watching = Some(talk);
                 }
+                continue;
+            }
+
+            let client = match clients.read().await.get(&id) {
+                Some(client) => client.clone(),
+                None => break,
+            };
+
+            match process_event(event, &db, &client, &config, &timer) {
+                Ok(response) => send_events(clients.clone(), response).await,
+                Err(err) => send_error(&clients, &id, &err).await,
             }
         }
     }
@@ -67,7 +92,7 @@ pub async fn garabage_collector(clients: Clients) {
     println!("Starting Garabage Collector");
 
     loop {
-        std::thread::sleep(Duration::from_secs(60));
+        tokio::time::sleep(Duration::from_secs(60)).await;
         {
             let mut writer = clients.write().await;
             let mut remove = Vec::new();
@@ -0,0 +1,122 @@
+use std::{env, fs, path::PathBuf, sync::Arc};
+
+use chrono::NaiveTime;
+
+use crate::validation::Limits;
+
+// Template used for the meeting minutes when MINUTES_TEMPLATE is not set
+const DEFAULT_MINUTES_TEMPLATE: &str = include_str!("../templates/minutes.md");
+
+pub type SharedConfig = Arc<Config>;
+
+#[derive(Debug)]
+pub struct Config {
+    // Markdown template for the meeting minutes, see templates/minutes.md
+    pub minutes_template: String,
+    // Password that authenticates a client as an admin, admins are disabled when unset
+    pub admin_password: Option<String>,
+    // Minutes of talk types by slug or label, copied into the talk_types table at startup
+    pub talk_durations: Vec<(String, i32)>,
+    // When meetings start and how many minutes they last
+    pub meeting_start: NaiveTime,
+    pub meeting_length: i64,
+    // Whether forum topics with more votes are presented first
+    pub sort_by_votes: bool,
+    // Where uploaded slides are stored, and how many bytes a file and all files of a talk may take
+    pub attachments_dir: PathBuf,
+    pub max_attachment_size: u64,
+    pub attachment_quota: u64,
+    // Longest names, descriptions and comments clients may send
+    pub limits: Limits,
+}
+
+impl Config {
+    pub fn from_env() -> Config {
+        let minutes_template = match env::var_os("MINUTES_TEMPLATE") {
+            Some(path) => {
+                fs::read_to_string(&path).expect("Minutes template could not be read")
+            }
+            None => {
+                String::from(DEFAULT_MINUTES_TEMPLATE)
+            }
+        };
+
+        let admin_password = env::var("ADMIN_PASSWORD").ok()
+            .filter(|password| !password.is_empty());
+
+        // e.g. TALK_DURATIONS="LightningTalk=7,ForumTopic=15"
+        let talk_durations = match env::var("TALK_DURATIONS") {
+            Ok(durations) => {
+                durations.split(',').filter(|item| !item.trim().is_empty()).map(parse_duration).collect()
+            }
+            Err(_) => {
+                Vec::new()
+            }
+        };
+
+        let meeting_start = match env::var("MEETING_START") {
+            Ok(start) => {
+                NaiveTime::parse_from_str(&start, "%H:%M").expect("MEETING_START must be formatted HH:MM")
+            }
+            Err(_) => {
+                NaiveTime::from_hms_opt(19, 0, 0).unwrap()
+            }
+        };
+
+        let meeting_length = match env::var("MEETING_LENGTH") {
+            Ok(length) => {
+                length.parse().expect("MEETING_LENGTH must be a number of minutes")
+            }
+            Err(_) => {
+                60
+            }
+        };
+
+        let sort_by_votes = env::var("SORT_BY_VOTES")
+            .is_ok_and(|sort| sort == "1" || sort.eq_ignore_ascii_case("true"));
+
+        let attachments_dir = env::var_os("ATTACHMENTS_DIR")
+            .map_or_else(|| PathBuf::from("attachments"), PathBuf::from);
+        let max_attachment_size = megabytes("ATTACHMENT_MAX_SIZE", 20);
+        let attachment_quota = megabytes("ATTACHMENT_QUOTA", 100);
+        let limits = Limits::from_env();
+
+        Config {
+            minutes_template,
+            admin_password,
+            talk_durations,
+            meeting_start,
+            meeting_length,
+            sort_by_votes,
+            attachments_dir,
+            max_attachment_size,
+            attachment_quota,
+            limits,
+        }
+    }
+}
+
+// Parses one `Type=minutes` item of TALK_DURATIONS
+fn parse_duration(item: &str) -> (String, i32) {
+    let (talk_type, minutes) = item.split_once('=')
+        .unwrap_or_else(|| panic!("TALK_DURATIONS item {} must be formatted Type=minutes", item));
+    let minutes = minutes.trim().parse().ok()
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or_else(|| panic!("TALK_DURATIONS item {} must be a positive number of minutes", item));
+
+    (talk_type.trim().to_string(), minutes)
+}
+
+// Reads a size in megabytes from the environment, in bytes
+fn megabytes(variable: &str, default: u64) -> u64 {
+    let megabytes = match env::var(variable) {
+        Ok(size) => {
+            size.parse().unwrap_or_else(|_| panic!("{} must be a number of megabytes", variable))
+        }
+        Err(_) => {
+            default
+        }
+    };
+
+    megabytes * 1024 * 1024
+}
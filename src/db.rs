@@ -1,7 +1,11 @@
-use diesel::{Connection, QueryDsl, RunQueryDsl, SqliteConnection, Table, r2d2::{ConnectionManager, PooledConnection}, result::Error};
+use std::collections::HashMap;
 
-use crate::{error::AppError, model::{CreateTalk, Talk}};
-use crate::diesel::ExpressionMethods;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use diesel::{Connection, OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection, r2d2::{ConnectionManager, PooledConnection}, result::{DatabaseErrorKind, Error}};
+
+use crate::{error::{AppError, ErrorType}, model::{Attachment, Comment, CreateAttachment, CreateComment, CreateRevision, CreateTalk, CreateTalkType, Link, Meeting, Presented, Presenter, PresenterSummary, RevisionRow, UpdateTalk, SearchResult, Talk, TagSummary, TalkPage, TalkLink, TalkPresenter, TalkQuery, TalkRevision, TalkRow, TalkSort, TalkStatus, TalkTag, TalkType, UpdateTalkType, Vote}, presenters::{joined_names, tidy_name}, tags::tidy_tag};
+use crate::diesel::{BoolExpressionMethods, EscapeExpressionMethods, ExpressionMethods, TextExpressionMethods};
 
 pub type PooledSqlite = PooledConnection<ConnectionManager<SqliteConnection>>;
 
@@ -13,6 +17,113 @@ pub fn last_insert_rowid(c: &SqliteConnection) -> i32 {
     }
 }
 
+// Proposed and rejected talks are only listed for moderators
+const UNLISTED: [TalkStatus; 2] = [TalkStatus::Proposed, TalkStatus::Rejected];
+const LISTED_SQL: &str = "talks.status NOT IN (0, 5)";
+
+// Talk revisions keep their lists as json
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    serde_json::to_string(value).map_err(|err| Error::SerializationError(Box::new(err)))
+}
+
+fn parse_revision(row: RevisionRow) -> Result<TalkRevision, Error> {
+    row.parse().map_err(|err| Error::DeserializationError(Box::new(err)))
+}
+
+// Characters wrapped around matching terms by the full-text index
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+// Turns user input into an FTS5 query, every word must match and the last one may be a prefix
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" ") + "*")
+    }
+}
+
+// Escapes a highlighted string from the full-text index, wrapping matches in <mark>
+fn highlight_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#x27;"),
+            _ => html.push(c),
+        }
+    }
+
+    html
+}
+
+// Page size of talk listings when none is requested
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// Position in a listing of talks, points at the last talk of a page
+struct Cursor {
+    position: i32,
+    talk_type: i32,
+    id: i32,
+}
+
+impl Cursor {
+    fn parse(cursor: &str) -> Result<Cursor, AppError> {
+        let invalid = || AppError::new(&format!("invalid cursor {}", cursor), ErrorType::BadRequest);
+
+        let parts: Vec<i32> = cursor.split('.')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        match parts[..] {
+            [position, talk_type, id] => Ok(Cursor { position, talk_type, id }),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn of(talk: &Talk) -> String {
+        format!("{}.{}.{}", talk.talk_type.position, talk.talk_type.id, talk.id)
+    }
+}
+
+#[derive(QueryableByName)]
+struct SearchRow {
+    #[diesel(embed)]
+    talk: TalkRow,
+    #[sql_type = "diesel::sql_types::Text"]
+    name_html: String,
+    #[sql_type = "diesel::sql_types::Text"]
+    snippet_html: String,
+    #[sql_type = "diesel::sql_types::Double"]
+    rank: f64,
+}
+
+#[derive(QueryableByName)]
+struct TypeCount {
+    #[sql_type = "diesel::sql_types::Integer"]
+    talk_type: i32,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    talks: i64,
+}
+
+#[derive(QueryableByName)]
+struct PresenterCount {
+    #[sql_type = "diesel::sql_types::Text"]
+    name: String,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    talks: i64,
+}
+
 pub struct DBManager {
     connection: PooledSqlite,
 }
@@ -22,14 +133,17 @@ impl DBManager {
         DBManager { connection }
     }
 
-    pub fn create_talk(&self, talk: CreateTalk) -> Result<i32, AppError> {
+    pub fn create_talk(&self, talk: CreateTalk, presenters: &[Presenter], links: &[Link], tags: &[String]) -> Result<i32, AppError> {
         use super::schema::talks;
 
         self.connection.transaction::<i32, _, _>(|| {
             let id = diesel::insert_into(talks::table) 
                 .values(&talk)
                 .execute(&self.connection)
-                .map(|_| last_insert_rowid(&self.connection));
+                .map(|_| last_insert_rowid(&self.connection))
+                .and_then(|id| self.link_presenters(id, presenters).map(|_| id))
+                .and_then(|id| self.set_links(id, links).map(|_| id))
+                .and_then(|id| self.set_tags(id, tags).map(|_| id));
 
             match id {
                 Ok(id) => { Ok(id) }
@@ -44,64 +158,1299 @@ impl DBManager {
         )
     }
 
-    pub fn list_visible_talks(&self) -> Result<Vec<Talk>, AppError> {
+    pub fn get_talk(&self, talk_id: i32) -> Result<Talk, AppError> {
         use super::schema::talks::dsl::*;
 
         talks
-            .filter(is_visible.eq(true))
-            .order(talk_type)
-            .load(&self.connection)
+            .find(talk_id)
+            .first::<TalkRow>(&self.connection)
+            .and_then(|row| self.attach_details(vec![row]))
+            .and_then(|mut found| found.pop().ok_or(Error::NotFound))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting talk {}", talk_id))
+            })
+    }
+
+    // Updates a talk, and replaces its presenters, links and tags when they are set
+    //
+    // The version being replaced is kept as a revision.
+    pub fn update_talk(&self, talk_id: i32, changes: UpdateTalk, presenters: Option<&[Presenter]>, links: Option<&[Link]>, tags: Option<&[String]>, now: NaiveDateTime) -> Result<usize, AppError> {
+        use super::schema::talks::dsl::*;
+
+        self.connection.transaction::<usize, Error, _>(|| {
+            if self.save_revision(talk_id, now)? == 0 {
+                return Ok(0);
+            }
+
+            let updated = diesel::update(talks.find(talk_id))
+                .set(updated_at.eq(now))
+                .execute(&self.connection)?;
+
+            // Diesel refuses to run an update without changes
+            if updated == 1 && !changes.is_empty() {
+                diesel::update(talks.find(talk_id))
+                    .set(&changes)
+                    .execute(&self.connection)?;
+            }
+
+            if let (1, Some(presenters)) = (updated, presenters) {
+                self.link_presenters(talk_id, presenters)?;
+            }
+            if let (1, Some(links)) = (updated, links) {
+                self.set_links(talk_id, links)?;
+            }
+            if let (1, Some(tags)) = (updated, tags) {
+                self.set_tags(talk_id, tags)?;
+            }
+
+            Ok(updated)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("updating talk {}", talk_id))
+        })
+    }
+
+    // Puts a talk back the way it was at a revision, keeping the version being replaced as a revision too
+    pub fn revert_talk(&self, talk_id: i32, revision: &TalkRevision, presenters: &[Presenter], now: NaiveDateTime) -> Result<usize, AppError> {
+        use super::schema::talks::dsl::*;
+
+        self.connection.transaction::<usize, Error, _>(|| {
+            if self.save_revision(talk_id, now)? == 0 {
+                return Ok(0);
+            }
+
+            diesel::update(talks.find(talk_id))
+                .set((
+                    name.eq(joined_names(presenters)),
+                    talk_type.eq(revision.talk_type),
+                    description.eq(&revision.description),
+                    duration.eq(revision.duration),
+                    updated_at.eq(now),
+                ))
+                .execute(&self.connection)?;
+            self.link_presenters(talk_id, presenters)?;
+            self.set_links(talk_id, &revision.links)?;
+            self.set_tags(talk_id, &revision.tags)?;
+
+            Ok(1)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("reverting talk {} to revision {}", talk_id, revision.id))
+        })
+    }
+
+    // Saves the current version of a talk as a revision, returns 0 when there is no such talk
+    fn save_revision(&self, talk: i32, now: NaiveDateTime) -> Result<usize, Error> {
+        use super::schema::{talk_revisions, talks};
+
+        let current = match talks::table.find(talk).first::<TalkRow>(&self.connection).optional()? {
+            Some(row) => self.attach_details(vec![row])?.pop().ok_or(Error::NotFound)?,
+            None => return Ok(0),
+        };
+
+        let names: Vec<&str> = current.presenters.iter().map(|presenter| presenter.name.as_str()).collect();
+        let revision = CreateRevision {
+            talk_id: talk,
+            name: &current.name,
+            talk_type: current.talk_type.id,
+            description: &current.description,
+            duration: current.duration,
+            presenters: to_json(&names)?,
+            links: to_json(&current.links)?,
+            tags: to_json(&current.tags)?,
+            replaced_at: now,
+        };
+
+        diesel::insert_into(talk_revisions::table)
+            .values(&revision)
+            .execute(&self.connection)
+    }
+
+    // Makes `presenters` the presenters of a talk, in that order
+    fn link_presenters(&self, talk: i32, presenters: &[Presenter]) -> Result<(), Error> {
+        use super::schema::talk_presenters::dsl::*;
+
+        let links: Vec<TalkPresenter> = presenters.iter()
+            .enumerate()
+            .map(|(i, presenter)| TalkPresenter { talk_id: talk, presenter_id: presenter.id, position: i as i32 })
+            .collect();
+
+        diesel::delete(talk_presenters.filter(talk_id.eq(talk))).execute(&self.connection)?;
+
+        // Batch inserts are only implemented for the sqlite connection itself
+        diesel::insert_into(talk_presenters).values(&links).execute(&*self.connection)?;
+        Ok(())
+    }
+
+    // Makes `links` the links of a talk, in that order
+    fn set_links(&self, talk: i32, links: &[Link]) -> Result<(), Error> {
+        use super::schema::links::{table, talk_id};
+
+        let rows: Vec<TalkLink> = links.iter()
+            .enumerate()
+            .map(|(i, link)| TalkLink { talk_id: talk, position: i as i32, kind: link.kind, url: &link.url })
+            .collect();
+
+        diesel::delete(table.filter(talk_id.eq(talk))).execute(&self.connection)?;
+
+        // Batch inserts are only implemented for the sqlite connection itself
+        diesel::insert_into(table).values(&rows).execute(&*self.connection)?;
+        Ok(())
+    }
+
+    // Makes `tags` the tags of a talk
+    fn set_tags(&self, talk: i32, tags: &[String]) -> Result<(), Error> {
+        use super::schema::tags::{table, talk_id};
+
+        let rows: Vec<TalkTag> = tags.iter()
+            .map(|tag| TalkTag { talk_id: talk, tag })
+            .collect();
+
+        diesel::delete(table.filter(talk_id.eq(talk))).execute(&self.connection)?;
+
+        // Batch inserts are only implemented for the sqlite connection itself
+        diesel::insert_into(table).values(&rows).execute(&*self.connection)?;
+        Ok(())
+    }
+
+    // Loads the type, presenters, links and tags of every talk, keeping the order of `rows`
+    fn attach_details(&self, rows: Vec<TalkRow>) -> Result<Vec<Talk>, Error> {
+        use super::schema::{links, presenters, tags, talk_presenters, talk_types};
+
+        let types: HashMap<i32, TalkType> = talk_types::table
+            .load::<TalkType>(&self.connection)?
+            .into_iter()
+            .map(|talk_type| (talk_type.id, talk_type))
+            .collect();
+        let ids: Vec<i32> = rows.iter().map(|row| row.id).collect();
+        let presented_by = talk_presenters::table
+            .inner_join(presenters::table)
+            .filter(talk_presenters::talk_id.eq_any(&ids))
+            .order((talk_presenters::talk_id, talk_presenters::position))
+            .select((talk_presenters::talk_id, (presenters::id, presenters::name)))
+            .load::<(i32, Presenter)>(&self.connection)?;
+        let linked = links::table
+            .filter(links::talk_id.eq_any(&ids))
+            .order((links::talk_id, links::position))
+            .select((links::talk_id, (links::kind, links::url)))
+            .load::<(i32, Link)>(&self.connection)?;
+        let tagged = tags::table
+            .filter(tags::talk_id.eq_any(&ids))
+            .order((tags::talk_id, tags::tag))
+            .load::<(i32, String)>(&self.connection)?;
+
+        let mut presenters_by_talk: HashMap<i32, Vec<Presenter>> = HashMap::new();
+        for (talk, presenter) in presented_by {
+            presenters_by_talk.entry(talk).or_default().push(presenter);
+        }
+        let mut links_by_talk: HashMap<i32, Vec<Link>> = HashMap::new();
+        for (talk, link) in linked {
+            links_by_talk.entry(talk).or_default().push(link);
+        }
+        let mut tags_by_talk: HashMap<i32, Vec<String>> = HashMap::new();
+        for (talk, tag) in tagged {
+            tags_by_talk.entry(talk).or_default().push(tag);
+        }
+
+        // Talk types in use cannot be deleted, see delete_talk_type
+        rows.into_iter()
+            .map(|row| {
+                let talk_type = types.get(&row.talk_type).cloned().ok_or(Error::NotFound)?;
+                let presenters = presenters_by_talk.remove(&row.id).unwrap_or_default();
+                let links = links_by_talk.remove(&row.id).unwrap_or_default();
+                let tags = tags_by_talk.remove(&row.id).unwrap_or_default();
+                Ok(row.with_details(talk_type, presenters, links, tags))
+            })
+            .collect()
+    }
+
+    // Visible talks in the running order, see present_next
+    //
+    // These are the talks of the upcoming meeting, including the ones already
+    // presented there until they are archived.
+    pub fn list_visible_talks(&self, by_votes: bool) -> Result<Vec<Talk>, AppError> {
+        use super::schema::{talk_types::position, talks::{all_columns, dsl::*}};
+
+        let visible = talks
+            .inner_join(super::schema::talk_types::table)
+            .select(all_columns)
+            .filter(meeting.is_null())
+            .filter(status.eq(TalkStatus::Scheduled)
+                .or(status.eq(TalkStatus::Presenting))
+                .or(status.eq(TalkStatus::Presented).and(presented_at.is_not_null())))
+            .into_boxed();
+        let ordered = if by_votes {
+            visible.order((position, talk_type, votes.desc(), id))
+        } else {
+            visible.order((position, talk_type, id))
+        };
+
+        ordered
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
             .map_err(|err| {
                 AppError::from_diesel_err(err, "listing visible talks")
             })
     }
 
-    #[allow(dead_code)]
-    pub fn list_all_talks(&self) -> Result<Vec<Talk>, AppError> {
+    // Lists the talks that were archived at the meeting on `date`
+    pub fn list_meeting_talks(&self, date: NaiveDate) -> Result<Vec<Talk>, AppError> {
+        use super::schema::{talk_types::{self, position}, talks::{all_columns, dsl::*}};
+
+        talks
+            .inner_join(talk_types::table)
+            .select(all_columns)
+            .filter(meeting.eq(date))
+            .order((position, talk_type, id))
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing talks for meeting {}", date))
+            })
+    }
+
+    // Searches talk names and descriptions, best matches first
+    pub fn search_talks(&self, input: &str, kind: Option<i32>, tag: Option<&str>, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<SearchResult>, AppError> {
+        use diesel::sql_types::{Date, Integer, Nullable, Text};
+
+        let query = match fts_query(input) {
+            Some(query) => query,
+            None => return Ok(Vec::new()),
+        };
+
+        diesel::sql_query(format!(
+            "SELECT talks.*, \
+                highlight(talks_fts, 0, '{start}', '{end}') AS name_html, \
+                snippet(talks_fts, 1, '{start}', '{end}', '…', 24) AS snippet_html, \
+                bm25(talks_fts) AS rank \
+             FROM talks_fts JOIN talks ON talks.id = talks_fts.rowid \
+             WHERE talks_fts MATCH ?1 \
+                AND (?2 IS NULL OR talks.talk_type = ?2) \
+                AND (?3 IS NULL OR talks.meeting >= ?3) \
+                AND (?4 IS NULL OR talks.meeting <= ?4) \
+                AND (?5 IS NULL OR talks.id IN (SELECT talk_id FROM tags WHERE tag = ?5)) \
+                AND {listed} AND talks.status <> {withdrawn} \
+             ORDER BY rank LIMIT 50",
+            start = MATCH_START, end = MATCH_END, listed = LISTED_SQL, withdrawn = TalkStatus::Withdrawn as i32))
+            .bind::<Text, _>(query)
+            .bind::<Nullable<Integer>, _>(kind)
+            .bind::<Nullable<Date>, _>(from)
+            .bind::<Nullable<Date>, _>(to)
+            .bind::<Nullable<Text>, _>(tag.map(tidy_tag))
+            .load::<SearchRow>(&self.connection)
+            .and_then(|results| {
+                let (rows, highlights): (Vec<TalkRow>, Vec<(String, String, f64)>) = results.into_iter()
+                    .map(|result| (result.talk, (result.name_html, result.snippet_html, result.rank)))
+                    .unzip();
+
+                Ok(self.attach_details(rows)?.into_iter()
+                    .zip(highlights)
+                    .map(|(talk, (name_html, snippet_html, rank))| SearchResult {
+                        talk,
+                        name_html: highlight_html(&name_html),
+                        snippet_html: highlight_html(&snippet_html),
+                        rank,
+                    })
+                    .collect())
+            })
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("searching talks for {}", input))
+            })
+    }
+
+    // Lists every past meeting, most recent first
+    pub fn list_meetings(&self) -> Result<Vec<Meeting>, AppError> {
+        diesel::sql_query(
+            "SELECT meeting AS date, COUNT(*) AS talks FROM talks \
+             WHERE meeting IS NOT NULL GROUP BY meeting ORDER BY meeting DESC")
+            .load(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing meetings")
+            })
+    }
+
+    // Number of talks of every type that has any, by talk type id
+    pub fn count_talks_by_type(&self) -> Result<Vec<(i32, i64)>, AppError> {
+        diesel::sql_query(format!("SELECT talk_type, COUNT(*) AS talks FROM talks WHERE {} GROUP BY talk_type ORDER BY talk_type", LISTED_SQL))
+            .load::<TypeCount>(&self.connection)
+            .map(|counts| counts.into_iter().map(|count| (count.talk_type, count.talks)).collect())
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "counting talks by type")
+            })
+    }
+
+    // Presenters with the most talks
+    pub fn count_talks_by_presenter(&self, limit: i64) -> Result<Vec<(String, i64)>, AppError> {
+        diesel::sql_query(format!(
+            "SELECT presenters.name, COUNT(*) AS talks FROM talk_presenters \
+             JOIN presenters ON presenters.id = talk_presenters.presenter_id \
+             JOIN talks ON talks.id = talk_presenters.talk_id \
+             WHERE {} \
+             GROUP BY presenters.id ORDER BY talks DESC, presenters.name LIMIT ?", LISTED_SQL))
+            .bind::<diesel::sql_types::BigInt, _>(limit)
+            .load::<PresenterCount>(&self.connection)
+            .map(|counts| counts.into_iter().map(|count| (count.name, count.talks)).collect())
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "counting talks by presenter")
+            })
+    }
+
+    // The meeting and talk type id of every archived talk
+    pub fn list_archived_talk_types(&self) -> Result<Vec<(NaiveDate, i32)>, AppError> {
+        use super::schema::talks::dsl::*;
+
+        talks
+            .select((meeting, talk_type))
+            .filter(meeting.is_not_null())
+            .load::<(Option<NaiveDate>, i32)>(&self.connection)
+            .map(|rows| rows.into_iter()
+                .filter_map(|(date, kind)| date.map(|date| (date, kind)))
+                .collect())
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing archived talks")
+            })
+    }
+
+    // Finds the meeting held before `date`
+    pub fn previous_meeting(&self, date: NaiveDate) -> Result<Option<NaiveDate>, AppError> {
+        use super::schema::talks::dsl::*;
+
+        talks
+            .select(meeting)
+            .filter(meeting.lt(date))
+            .order(meeting.desc())
+            .first::<Option<NaiveDate>>(&self.connection)
+            .optional()
+            .map(Option::flatten)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("finding the meeting before {}", date))
+            })
+    }
+
+    // Finds the meeting held after `date`
+    pub fn next_meeting(&self, date: NaiveDate) -> Result<Option<NaiveDate>, AppError> {
+        use super::schema::talks::dsl::*;
+
+        talks
+            .select(meeting)
+            .filter(meeting.gt(date))
+            .order(meeting.asc())
+            .first::<Option<NaiveDate>>(&self.connection)
+            .optional()
+            .map(Option::flatten)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("finding the meeting after {}", date))
+            })
+    }
+
+    // Lists one page of talks matching the query
+    pub fn list_talks(&self, query: &TalkQuery) -> Result<TalkPage, AppError> {
+        use super::schema::{tags, talk_types::{self, position}, talks::{all_columns, dsl::*}};
+
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let mut select = talks
+            .inner_join(talk_types::table)
+            .select(all_columns)
+            .filter(status.ne_all(UNLISTED.to_vec()))
+            .into_boxed();
+
+        // Withdrawn talks are only listed when asked for
+        select = match (query.status, query.visible) {
+            (Some(wanted), _) => select.filter(status.eq(wanted)),
+            (None, Some(true)) => select.filter(status.eq_any(vec![TalkStatus::Scheduled, TalkStatus::Presenting])),
+            (None, Some(false)) => select.filter(status.eq(TalkStatus::Presented)),
+            (None, None) => select.filter(status.ne(TalkStatus::Withdrawn)),
+        };
+        if let Some(kind) = &query.talk_type {
+            select = select.filter(talk_type.eq(self.find_talk_type(kind)?.id));
+        }
+        if let Some(date) = query.meeting {
+            select = select.filter(meeting.eq(date));
+        }
+        if let Some(presenter) = &query.presenter {
+            let pattern = format!("%{}%", presenter.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+            select = select.filter(name.like(pattern).escape('\\'));
+        }
+        if let Some(tag) = &query.tag {
+            let tagged = tags::table.select(tags::talk_id).filter(tags::tag.eq(tidy_tag(tag)));
+            select = select.filter(id.eq_any(tagged));
+        }
+
+        // Keyset pagination, continue after the last talk of the previous page
+        if let Some(cursor) = &query.cursor {
+            let cursor = Cursor::parse(cursor)?;
+            select = match query.sort {
+                TalkSort::Oldest => select.filter(id.gt(cursor.id)),
+                TalkSort::Newest => select.filter(id.lt(cursor.id)),
+                TalkSort::Type => select.filter(position.gt(cursor.position)
+                    .or(position.eq(cursor.position).and(talk_type.gt(cursor.talk_type)))
+                    .or(position.eq(cursor.position).and(talk_type.eq(cursor.talk_type)).and(id.gt(cursor.id)))),
+            };
+        }
+
+        select = match query.sort {
+            TalkSort::Oldest => select.order(id.asc()),
+            TalkSort::Newest => select.order(id.desc()),
+            TalkSort::Type => select.order((position.asc(), talk_type.asc(), id.asc())),
+        };
+
+        let mut page: Vec<Talk> = select
+            .limit(limit + 1)
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing talks")
+            })?;
+
+        // The extra talk only tells us whether there is another page
+        let next_cursor = if page.len() as i64 > limit {
+            page.truncate(limit as usize);
+            page.last().map(Cursor::of)
+        } else {
+            None
+        };
+
+        Ok(TalkPage { talks: page, next_cursor })
+    }
+
+    // Talks proposed by visitors that wait for a moderator, oldest first
+    pub fn list_proposals(&self) -> Result<Vec<Talk>, AppError> {
         use super::schema::talks::dsl::*;
 
         talks
-            .select(talks::all_columns())
+            .filter(status.eq(TalkStatus::Proposed))
+            .order(id.asc())
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing proposed talks")
+            })
+    }
+
+    // Every earlier version of a talk, newest first
+    pub fn list_revisions(&self, talk: i32) -> Result<Vec<TalkRevision>, AppError> {
+        use super::schema::talk_revisions::dsl::*;
+
+        talk_revisions
+            .filter(talk_id.eq(talk))
+            .order(id.desc())
+            .load::<RevisionRow>(&self.connection)
+            .and_then(|rows| rows.into_iter().map(parse_revision).collect())
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing revisions of talk {}", talk))
+            })
+    }
+
+    pub fn get_revision(&self, revision_id: i32) -> Result<TalkRevision, AppError> {
+        use super::schema::talk_revisions::dsl::*;
+
+        talk_revisions
+            .find(revision_id)
+            .first::<RevisionRow>(&self.connection)
+            .and_then(parse_revision)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting revision {}", revision_id))
+            })
+    }
+
+    pub fn get_presenter(&self, presenter_id: i32) -> Result<Presenter, AppError> {
+        use super::schema::presenters::dsl::*;
+
+        presenters
+            .find(presenter_id)
+            .first(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting presenter {}", presenter_id))
+            })
+    }
+
+    // Finds the presenter with this name ignoring case, or adds a new one
+    pub fn find_or_create_presenter(&self, presenter_name: &str) -> Result<Presenter, AppError> {
+        use super::schema::presenters::dsl::*;
+
+        let tidy = tidy_name(presenter_name);
+        self.connection.transaction::<Presenter, Error, _>(|| {
+            let existing = diesel::sql_query("SELECT * FROM presenters WHERE name = ? COLLATE NOCASE")
+                .bind::<diesel::sql_types::Text, _>(&tidy)
+                .get_result::<Presenter>(&self.connection)
+                .optional()?;
+
+            match existing {
+                Some(presenter) => { Ok(presenter) }
+                None => {
+                    diesel::insert_into(presenters)
+                        .values(name.eq(&tidy))
+                        .execute(&self.connection)?;
+
+                    Ok(Presenter { id: last_insert_rowid(&self.connection), name: tidy.clone() })
+                }
+            }
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("finding presenter {}", tidy))
+        })
+    }
+
+    // Finds or adds the presenter of every name, skipping blank and repeated names
+    pub fn find_or_create_presenters(&self, names: &[String]) -> Result<Vec<Presenter>, AppError> {
+        let mut found: Vec<Presenter> = Vec::new();
+
+        for presenter_name in names.iter().filter(|presenter_name| !presenter_name.trim().is_empty()) {
+            let presenter = self.find_or_create_presenter(presenter_name)?;
+            if !found.iter().any(|existing| existing.id == presenter.id) {
+                found.push(presenter);
+            }
+        }
+
+        Ok(found)
+    }
+
+    // Every presenter who has given a talk, by name
+    pub fn list_presenters(&self) -> Result<Vec<PresenterSummary>, AppError> {
+        diesel::sql_query(format!(
+            "SELECT presenters.id, presenters.name, COUNT(*) AS talks FROM presenters \
+             JOIN talk_presenters ON talk_presenters.presenter_id = presenters.id \
+             JOIN talks ON talks.id = talk_presenters.talk_id \
+             WHERE {} \
+             GROUP BY presenters.id ORDER BY presenters.name COLLATE NOCASE", LISTED_SQL))
             .load(&self.connection)
             .map_err(|err| {
-                AppError::from_diesel_err(err, "listing all talks")
+                AppError::from_diesel_err(err, "listing presenters")
             })
     }
 
-    pub fn hide_talk(&self, talk_id: i32) -> Result<usize, AppError> {
+    // Every talk of a presenter, newest first
+    pub fn list_presenter_talks(&self, presenter: i32) -> Result<Vec<Talk>, AppError> {
+        use super::schema::{talk_presenters, talks::dsl::*};
+
+        let given = talk_presenters::table
+            .select(talk_presenters::talk_id)
+            .filter(talk_presenters::presenter_id.eq(presenter));
+
+        talks
+            .filter(id.eq_any(given))
+            .filter(status.ne_all(UNLISTED.to_vec()))
+            .order(id.desc())
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing talks of presenter {}", presenter))
+            })
+    }
+
+    // Every tag in use and how many talks have it, alphabetically
+    pub fn list_tags(&self) -> Result<Vec<TagSummary>, AppError> {
+        diesel::sql_query(format!(
+            "SELECT tag, COUNT(*) AS talks FROM tags JOIN talks ON talks.id = tags.talk_id \
+             WHERE {} GROUP BY tag ORDER BY tag", LISTED_SQL))
+            .load(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing tags")
+            })
+    }
+
+    // Every talk with a tag, newest first
+    pub fn list_tag_talks(&self, tag: &str) -> Result<Vec<Talk>, AppError> {
+        use super::schema::{tags, talks::dsl::*};
+
+        let tagged = tags::table
+            .select(tags::talk_id)
+            .filter(tags::tag.eq(tag));
+
+        talks
+            .filter(id.eq_any(tagged))
+            .filter(status.ne_all(UNLISTED.to_vec()))
+            .order(id.desc())
+            .load::<TalkRow>(&self.connection)
+            .and_then(|rows| self.attach_details(rows))
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing talks tagged {}", tag))
+            })
+    }
+
+    // Moves every talk of presenter `from` to `into` and removes `from`
+    //
+    // Returns the remaining presenter and the talks that were moved.
+    pub fn merge_presenters(&self, from: i32, into: i32, now: NaiveDateTime) -> Result<(Presenter, Vec<Talk>), AppError> {
+        use super::schema::{presenters, talk_presenters, talks};
+
+        self.connection.transaction::<_, Error, _>(|| {
+            let presenter: Presenter = presenters::table.find(into).first(&self.connection)?;
+            presenters::table.find(from).select(presenters::id).first::<i32>(&self.connection)?;
+
+            let moved = talk_presenters::table
+                .select(talk_presenters::talk_id)
+                .filter(talk_presenters::presenter_id.eq(from))
+                .load::<i32>(&self.connection)?;
+            for talk in &moved {
+                self.save_revision(*talk, now)?;
+            }
+
+            // Talks given by both only keep `into`
+            let shared = talk_presenters::table
+                .select(talk_presenters::talk_id)
+                .filter(talk_presenters::presenter_id.eq(into))
+                .load::<i32>(&self.connection)?;
+            diesel::delete(talk_presenters::table
+                .filter(talk_presenters::presenter_id.eq(from))
+                .filter(talk_presenters::talk_id.eq_any(shared)))
+                .execute(&self.connection)?;
+
+            diesel::update(talk_presenters::table.filter(talk_presenters::presenter_id.eq(from)))
+                .set(talk_presenters::presenter_id.eq(into))
+                .execute(&self.connection)?;
+            diesel::delete(presenters::table.find(from)).execute(&self.connection)?;
+
+            // The names shown on the moved talks change with their presenters
+            let rows = talks::table
+                .filter(talks::id.eq_any(moved))
+                .order(talks::id)
+                .load::<TalkRow>(&self.connection)?;
+
+            let mut renamed = self.attach_details(rows)?;
+            for talk in renamed.iter_mut() {
+                talk.name = joined_names(&talk.presenters);
+                talk.updated_at = now;
+                diesel::update(talks::table.find(talk.id))
+                    .set((talks::name.eq(&talk.name), talks::updated_at.eq(now)))
+                    .execute(&self.connection)?;
+            }
+
+            Ok((presenter, renamed))
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("merging presenter {} into {}", from, into))
+        })
+    }
+
+    // Finishes the talk being presented and starts the next one in the running order
+    //
+    // With `finish` unset the talk being presented, if any, keeps going. Talks
+    // are presented by type, forum topics with the most votes first when
+    // `by_votes` is set.
+    pub fn present_next(&self, finish: bool, by_votes: bool, now: NaiveDateTime) -> Result<(), AppError> {
+        use super::schema::{talk_types::{self, position}, talks::dsl::*};
+
+        self.connection.transaction::<(), Error, _>(|| {
+            let current = talks
+                .select(id)
+                .filter(status.eq(TalkStatus::Presenting))
+                .first::<i32>(&self.connection)
+                .optional()?;
+
+            match (current, finish) {
+                (Some(_), false) => { return Ok(()); }
+                (Some(current), true) => {
+                    self.change_status(current, TalkStatus::Presenting, TalkStatus::Presented, now)?;
+                }
+                (None, _) => {}
+            }
+
+            let waiting = talks
+                .inner_join(talk_types::table)
+                .select(id)
+                .filter(status.eq(TalkStatus::Scheduled))
+                .into_boxed();
+            let ordered = if by_votes {
+                waiting.order((position, talk_type, votes.desc(), id))
+            } else {
+                waiting.order((position, talk_type, id))
+            };
+            let next = ordered
+                .first::<i32>(&self.connection)
+                .optional()?;
+
+            if let Some(next) = next {
+                self.change_status(next, TalkStatus::Scheduled, TalkStatus::Presenting, now)?;
+            }
+
+            Ok(())
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, "starting the next talk")
+        })
+    }
+
+    // Puts the talk being presented back in the running order and presents the last finished talk again
+    pub fn present_previous(&self, now: NaiveDateTime) -> Result<(), AppError> {
         use super::schema::talks::dsl::*;
 
-        let talk = talks.find(talk_id);
-        diesel::update(talk)
+        self.connection.transaction::<(), Error, _>(|| {
+            let current = talks
+                .select(id)
+                .filter(status.eq(TalkStatus::Presenting))
+                .load::<i32>(&self.connection)?;
+            for current in current {
+                self.change_status(current, TalkStatus::Presenting, TalkStatus::Scheduled, now)?;
+            }
+
+            let previous = talks
+                .select(id)
+                .filter(status.eq(TalkStatus::Presented).and(meeting.is_null()).and(presented_at.is_not_null()))
+                .order((presented_at.desc(), id.desc()))
+                .first::<i32>(&self.connection)
+                .optional()?;
+
+            if let Some(previous) = previous {
+                self.change_status(previous, TalkStatus::Presented, TalkStatus::Presenting, now)?;
+            }
+
+            Ok(())
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, "starting the previous talk")
+        })
+    }
+
+    // The visible talk being presented and the visible talks that are done
+    pub fn presenting(&self) -> Result<(Option<i32>, Vec<Presented>), AppError> {
+        use super::schema::{talk_types::{self, position}, talks::dsl::*};
+
+        talks
+            .inner_join(talk_types::table)
+            .select((id, started_at, presented_at))
+            .filter(meeting.is_null().and(started_at.is_not_null()))
+            .filter(status.eq(TalkStatus::Presenting).or(status.eq(TalkStatus::Presented)))
+            .order((position, talk_type, id))
+            .load::<(i32, Option<NaiveDateTime>, Option<NaiveDateTime>)>(&self.connection)
+            .map(|rows| {
+                let current = rows.iter().find(|(_, _, done)| done.is_none()).map(|(talk, _, _)| *talk);
+                let presented = rows.into_iter()
+                    .filter_map(|(talk, _, done)| done.map(|done| Presented { id: talk, presented_at: done }))
+                    .collect();
+
+                (current, presented)
+            })
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing presented talks")
+            })
+    }
+
+    // Counts a client's vote for a talk, None when the client already voted for it
+    //
+    // Client ids are handed out again on every page load, so this only stops a page from voting twice.
+    pub fn vote(&self, talk_id: i32, client_id: &str, now: NaiveDateTime) -> Result<Option<i32>, AppError> {
+        use super::schema::{talks, votes};
+
+        self.connection.transaction::<Option<i32>, Error, _>(|| {
+            let inserted = diesel::insert_into(votes::table)
+                .values(&Vote { talk_id, client_id })
+                .execute(&self.connection);
+
+            match inserted {
+                Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => { return Ok(None); }
+                other => { other?; }
+            }
+
+            diesel::update(talks::table.find(talk_id))
+                .set((talks::votes.eq(talks::votes + 1), talks::updated_at.eq(now)))
+                .execute(&self.connection)?;
+
+            talks::table
+                .find(talk_id)
+                .select(talks::votes)
+                .first::<i32>(&self.connection)
+                .map(Some)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("voting for talk {}", talk_id))
+        })
+    }
+
+    pub fn create_comment(&self, comment: CreateComment) -> Result<Comment, AppError> {
+        use super::schema::comments;
+
+        self.connection.transaction::<Comment, Error, _>(|| {
+            diesel::insert_into(comments::table)
+                .values(&comment)
+                .execute(&self.connection)?;
+
+            comments::table
+                .find(last_insert_rowid(&self.connection))
+                .first::<Comment>(&self.connection)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("commenting on talk {}", comment.talk_id))
+        })
+    }
+
+    pub fn get_comment(&self, comment_id: i32) -> Result<Comment, AppError> {
+        use super::schema::comments::dsl::*;
+
+        comments
+            .find(comment_id)
+            .first::<Comment>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting comment {}", comment_id))
+            })
+    }
+
+    // Visible comments on a talk, oldest first
+    pub fn list_comments(&self, talk: i32) -> Result<Vec<Comment>, AppError> {
+        use super::schema::comments::dsl::*;
+
+        comments
+            .filter(talk_id.eq(talk).and(is_visible.eq(true)))
+            .order(id)
+            .load::<Comment>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing comments on talk {}", talk))
+            })
+    }
+
+    pub fn hide_comment(&self, comment_id: i32) -> Result<usize, AppError> {
+        use super::schema::comments::dsl::*;
+
+        diesel::update(comments.find(comment_id))
             .set(is_visible.eq(false))
             .execute(&self.connection)
             .map_err(|err| {
-                AppError::from_diesel_err(err, &format!("unhiding talk {}", talk_id))
+                AppError::from_diesel_err(err, &format!("hiding comment {}", comment_id))
             })
     }
 
-    #[allow(dead_code)]
-    pub fn unhide_talk(&self, talk_id: i32) -> Result<usize, AppError> {
-        use super::schema::talks::dsl::*;
+    pub fn create_attachment(&self, attachment: CreateAttachment) -> Result<Attachment, AppError> {
+        use super::schema::attachments;
+
+        self.connection.transaction::<Attachment, Error, _>(|| {
+            diesel::insert_into(attachments::table)
+                .values(&attachment)
+                .execute(&self.connection)?;
 
-        let talk = talks.find(talk_id);
-        diesel::update(talk)
-            .set(is_visible.eq(true))
+            attachments::table
+                .find(last_insert_rowid(&self.connection))
+                .first::<Attachment>(&self.connection)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("attaching a file to talk {}", attachment.talk_id))
+        })
+    }
+
+    pub fn get_attachment(&self, attachment_id: i32) -> Result<Attachment, AppError> {
+        use super::schema::attachments::dsl::*;
+
+        attachments
+            .find(attachment_id)
+            .first::<Attachment>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting attachment {}", attachment_id))
+            })
+    }
+
+    // Attachments of a talk, oldest first
+    pub fn list_attachments(&self, talk: i32) -> Result<Vec<Attachment>, AppError> {
+        use super::schema::attachments::dsl::*;
+
+        attachments
+            .filter(talk_id.eq(talk))
+            .order(id)
+            .load::<Attachment>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("listing attachments of talk {}", talk))
+            })
+    }
+
+    // The same file uploaded to a talk before
+    pub fn find_attachment(&self, talk: i32, hash: &str) -> Result<Option<Attachment>, AppError> {
+        use super::schema::attachments::dsl::*;
+
+        attachments
+            .filter(talk_id.eq(talk).and(sha256.eq(hash)))
+            .first::<Attachment>(&self.connection)
+            .optional()
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("finding attachment of talk {}", talk))
+            })
+    }
+
+    // Bytes taken by all attachments of a talk
+    pub fn attachments_size(&self, talk: i32) -> Result<i64, AppError> {
+        use super::schema::attachments::dsl::*;
+
+        attachments
+            .filter(talk_id.eq(talk))
+            .select(size)
+            .load::<i64>(&self.connection)
+            .map(|sizes| sizes.iter().sum())
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("sizing attachments of talk {}", talk))
+            })
+    }
+
+    // Every talk type in the order talks are presented
+    pub fn list_talk_types(&self) -> Result<Vec<TalkType>, AppError> {
+        use super::schema::talk_types::dsl::*;
+
+        talk_types
+            .order((position, id))
+            .load::<TalkType>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, "listing talk types")
+            })
+    }
+
+    pub fn get_talk_type(&self, type_id: i32) -> Result<TalkType, AppError> {
+        use super::schema::talk_types::dsl::*;
+
+        talk_types
+            .find(type_id)
+            .first::<TalkType>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting talk type {}", type_id))
+            })
+    }
+
+    // Finds a talk type by its slug or label
+    pub fn find_talk_type(&self, name: &str) -> Result<TalkType, AppError> {
+        self.list_talk_types()?
+            .into_iter()
+            .find(|talk_type| talk_type.is_named(name))
+            .ok_or_else(|| AppError::new(&format!("unknown talk type {}", name), ErrorType::BadRequest))
+    }
+
+    pub fn create_talk_type(&self, talk_type: CreateTalkType) -> Result<TalkType, AppError> {
+        use super::schema::talk_types;
+
+        self.connection.transaction::<TalkType, Error, _>(|| {
+            diesel::insert_into(talk_types::table)
+                .values(&talk_type)
+                .execute(&self.connection)?;
+
+            talk_types::table
+                .find(last_insert_rowid(&self.connection))
+                .first::<TalkType>(&self.connection)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("creating talk type {}", talk_type.slug))
+        })
+    }
+
+    pub fn update_talk_type(&self, type_id: i32, changes: UpdateTalkType) -> Result<usize, AppError> {
+        use super::schema::talk_types::dsl::*;
+
+        diesel::update(talk_types.find(type_id))
+            .set(&changes)
             .execute(&self.connection)
             .map_err(|err| {
-                AppError::from_diesel_err(err, &format!("unhiding talk {}", talk_id))
+                AppError::from_diesel_err(err, &format!("updating talk type {}", type_id))
             })
     }
 
-    #[allow(dead_code)]
-    pub fn delete_talk(&self, talk_id: i32) -> Result<usize, AppError> {
-        use super::schema::talks::dsl::*;
+    // Talk types are only deleted while no talk has them
+    pub fn delete_talk_type(&self, type_id: i32) -> Result<usize, AppError> {
+        use super::schema::{talk_types, talks};
+
+        let used = talks::table
+            .filter(talks::talk_type.eq(type_id))
+            .count()
+            .get_result::<i64>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("counting talks of type {}", type_id))
+            })?;
+        if used > 0 {
+            return Err(AppError::new(&format!("talk type {} is used by {} talks", type_id, used), ErrorType::BadRequest));
+        }
 
-        let talk = talks.find(talk_id);
-        diesel::delete(talk)
+        diesel::delete(talk_types::table.find(type_id))
             .execute(&self.connection)
             .map_err(|err| {
-                AppError::from_diesel_err(err, &format!("deleting talk {}", talk_id))
+                AppError::from_diesel_err(err, &format!("deleting talk type {}", type_id))
             })
     }
+
+    // Hiding a talk archives it under today's meeting
+    //
+    // Only talks of the upcoming meeting can be hidden, the ones that were not
+    // presented yet count as presented at it.
+    pub fn hide_talk(&self, talk_id: i32, now: NaiveDateTime) -> Result<usize, AppError> {
+        use super::schema::talks::dsl::*;
+
+        let current = match self.talk_status(talk_id)? {
+            Some(current) => current,
+            None => return Ok(0),
+        };
+        if !current.can_become(TalkStatus::Presented) && current != TalkStatus::Presented {
+            return Err(AppError::new(&format!("talk {} is {} and cannot be hidden", talk_id, current), ErrorType::BadRequest));
+        }
+
+        self.connection.transaction::<usize, Error, _>(|| {
+            if current != TalkStatus::Presented && self.change_status(talk_id, current, TalkStatus::Presented, now)? == 0 {
+                return Ok(0);
+            }
+
+            diesel::update(talks.find(talk_id))
+                .set((meeting.eq(now.date()), hidden_at.eq(now), updated_at.eq(now)))
+                .execute(&self.connection)
+        }).map_err(|err| {
+            AppError::from_diesel_err(err, &format!("hiding talk {}", talk_id))
+        })
+    }
+
+    // Approves a proposed talk by scheduling it, or rejects it
+    //
+    // Returns the client id of the visitor who proposed it.
+    pub fn moderate_talk(&self, talk_id: i32, next: TalkStatus, now: NaiveDateTime) -> Result<Option<String>, AppError> {
+        use super::schema::talks::dsl::*;
+
+        let row = talks
+            .find(talk_id)
+            .first::<TalkRow>(&self.connection)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting talk {}", talk_id))
+            })?;
+        if row.status != TalkStatus::Proposed {
+            return Err(AppError::new(&format!("talk {} is {}, only proposed talks are moderated", talk_id, row.status), ErrorType::BadRequest));
+        }
+
+        let changed = self.change_status(talk_id, row.status, next, now)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("moderating talk {}", talk_id))
+            })?;
+        if changed == 0 {
+            return Err(AppError::new(&format!("talk {} was moderated in the meantime", talk_id), ErrorType::BadRequest));
+        }
+
+        Ok(row.submitted_by)
+    }
+
+    fn talk_status(&self, talk_id: i32) -> Result<Option<TalkStatus>, AppError> {
+        use super::schema::talks::dsl::*;
+
+        talks
+            .find(talk_id)
+            .select(status)
+            .first::<TalkStatus>(&self.connection)
+            .optional()
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("getting the status of talk {}", talk_id))
+            })
+    }
+
+    // Moves a talk to status `next`, refusing the transitions TalkStatus::can_become does not allow
+    //
+    // Returns the status the talk had.
+    pub fn set_status(&self, talk_id: i32, next: TalkStatus, now: NaiveDateTime) -> Result<TalkStatus, AppError> {
+        let current = self.talk_status(talk_id)?
+            .ok_or_else(|| AppError::new(&format!("talk {} does not exist", talk_id), ErrorType::NotFound))?;
+        if current == TalkStatus::Proposed {
+            return Err(AppError::new(&format!("talk {} is waiting for a moderator to approve or reject it", talk_id), ErrorType::BadRequest));
+        }
+        if !current.can_become(next) {
+            return Err(AppError::new(&format!("talk {} is {} and cannot become {}", talk_id, current, next), ErrorType::BadRequest));
+        }
+
+        let changed = self.change_status(talk_id, current, next, now)
+            .map_err(|err| {
+                AppError::from_diesel_err(err, &format!("changing the status of talk {}", talk_id))
+            })?;
+        if changed == 0 {
+            return Err(AppError::new(&format!("talk {} changed in the meantime, try again", talk_id), ErrorType::BadRequest));
+        }
+
+        Ok(current)
+    }
+
+    // Moves a talk from status `from` to `to` and keeps its presentation times in step
+    //
+    // Returns 0 when the talk no longer has status `from`.
+    fn change_status(&self, talk_id: i32, from: TalkStatus, to: TalkStatus, now: NaiveDateTime) -> Result<usize, Error> {
+        use super::schema::talks::dsl::*;
+
+        let talk = talks.find(talk_id).filter(status.eq(from));
+        match (from, to) {
+            (_, TalkStatus::Presenting) => {
+                diesel::update(talk)
+                    .set((status.eq(to), started_at.eq(now), presented_at.eq(None::<NaiveDateTime>), updated_at.eq(now)))
+                    .execute(&self.connection)
+            }
+            (TalkStatus::Presenting, TalkStatus::Presented) => {
+                diesel::update(talk)
+                    .set((status.eq(to), presented_at.eq(now), updated_at.eq(now)))
+                    .execute(&self.connection)
+            }
+            (TalkStatus::Presenting, TalkStatus::Scheduled) => {
+                diesel::update(talk)
+                    .set((status.eq(to), started_at.eq(None::<NaiveDateTime>), updated_at.eq(now)))
+                    .execute(&self.connection)
+            }
+            _ => {
+                diesel::update(talk)
+                    .set((status.eq(to), updated_at.eq(now)))
+                    .execute(&self.connection)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::fs;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use diesel::{SqliteConnection, connection::SimpleConnection, r2d2::{ConnectionManager, Pool}};
+
+    use super::DBManager;
+    use crate::model::{CreateTalk, CreateTalkType, TalkQuery, TalkSort, TalkStatus, UpdateTalk};
+    use crate::presenters::joined_names;
+
+    // A database in memory with every migration applied
+    pub fn memory_db() -> DBManager {
+        // Every connection to :memory: is a database of its own, so the pool keeps just one
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .unwrap();
+        let connection = pool.get().unwrap();
+
+        let mut migrations: Vec<_> = fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations")).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+        migrations.sort();
+        for migration in migrations {
+            connection.batch_execute(&fs::read_to_string(migration.join("up.sql")).unwrap()).unwrap();
+        }
+
+        DBManager::new(connection)
+    }
+
+    pub fn time(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    // Adds a talk given by `presenters`, returning its id
+    pub fn add_talk(db: &DBManager, presenters: &[&str], talk_type: i32, description: &str, status: TalkStatus) -> i32 {
+        let names: Vec<String> = presenters.iter().map(|name| name.to_string()).collect();
+        let presenters = db.find_or_create_presenters(&names).unwrap();
+        let name = joined_names(&presenters);
+        let description = description.to_string();
+        let talk = CreateTalk { name: &name, talk_type, description: &description, status, duration: None, submitted_by: None, created_at: time(18), updated_at: time(18) };
+
+        db.create_talk(talk, &presenters, &[], &[]).unwrap()
+    }
+
+    // Follows the cursors of a listing to the end, returning the ids in the order they were listed
+    fn every_page(db: &DBManager, sort: TalkSort, limit: i64) -> Vec<i32> {
+        let mut ids = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let query = TalkQuery { sort, cursor, limit: Some(limit), ..TalkQuery::default() };
+            let page = db.list_talks(&query).unwrap();
+            assert!(page.talks.len() as i64 <= limit);
+            ids.extend(page.talks.iter().map(|talk| talk.id));
+            // A cursor that goes back to earlier talks would page forever
+            assert!(ids.len() <= 100, "the pages do not end: {:?}", ids);
+
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                return ids;
+            }
+        }
+    }
+
+    #[test]
+    fn pages_by_type_continue_after_equal_sort_keys() {
+        let db = memory_db();
+        // A second type at the same position as lightning talks
+        let demo = db.create_talk_type(CreateTalkType { slug: "Demo", label: "demo", heading: "Demos", position: 2, color: "#000000", duration: 5, accepts_votes: false, after_meeting: false }).unwrap();
+
+        let lightning: Vec<i32> = (0..4).map(|i| add_talk(&db, &[&format!("Lightning {}", i)], 1, "", TalkStatus::Scheduled)).collect();
+        let demos: Vec<i32> = (0..3).map(|i| add_talk(&db, &[&format!("Demo {}", i)], demo.id, "", TalkStatus::Scheduled)).collect();
+        let topics: Vec<i32> = (0..2).map(|i| add_talk(&db, &[&format!("Topic {}", i)], 0, "", TalkStatus::Scheduled)).collect();
+        // Forum topics come first, then lightning talks and demos ordered by type id
+        let expected: Vec<i32> = topics.iter().chain(&lightning).chain(&demos).copied().collect();
+
+        for limit in 1..=expected.len() as i64 + 1 {
+            assert_eq!(every_page(&db, TalkSort::Type, limit), expected, "limit {}", limit);
+        }
+    }
+
+    #[test]
+    fn pages_by_id_skip_unlisted_talks() {
+        let db = memory_db();
+        let first = add_talk(&db, &["Alice"], 1, "", TalkStatus::Scheduled);
+        add_talk(&db, &["Mallory"], 1, "", TalkStatus::Proposed);
+        let second = add_talk(&db, &["Bob"], 1, "", TalkStatus::Presented);
+        add_talk(&db, &["Carol"], 1, "", TalkStatus::Withdrawn);
+        let third = add_talk(&db, &["Dave"], 1, "", TalkStatus::Scheduled);
+
+        assert_eq!(every_page(&db, TalkSort::Oldest, 1), vec![first, second, third]);
+        assert_eq!(every_page(&db, TalkSort::Newest, 2), vec![third, second, first]);
+    }
+
+    #[test]
+    fn merging_presenters_moves_their_talks() {
+        let db = memory_db();
+        let alone = add_talk(&db, &["A. Smith"], 1, "", TalkStatus::Scheduled);
+        let together = add_talk(&db, &["A. Smith", "Alice Smith", "Bob"], 1, "", TalkStatus::Scheduled);
+        let untouched = add_talk(&db, &["Alice Smith"], 1, "", TalkStatus::Scheduled);
+        let from = db.get_talk(alone).unwrap().presenters[0].id;
+        let into = db.get_talk(untouched).unwrap().presenters[0].id;
+
+        let (presenter, moved) = db.merge_presenters(from, into, time(19)).unwrap();
+        assert_eq!(presenter.id, into);
+        assert_eq!(moved.iter().map(|talk| talk.id).collect::<Vec<_>>(), vec![alone, together]);
+        assert!(db.get_presenter(from).is_err());
+
+        let alone = db.get_talk(alone).unwrap();
+        assert_eq!(alone.presenters.iter().map(|presenter| presenter.id).collect::<Vec<_>>(), vec![into]);
+        assert_eq!(alone.name, "Alice Smith");
+        assert_eq!(alone.updated_at, time(19));
+
+        // A talk given by both lists the remaining presenter once
+        let together = db.get_talk(together).unwrap();
+        let names: Vec<&str> = together.presenters.iter().map(|presenter| presenter.name.as_str()).collect();
+        assert_eq!(names, vec!["Alice Smith", "Bob"]);
+        assert_eq!(together.name, joined_names(&together.presenters));
+
+        // The old names can be brought back from a revision
+        assert_eq!(db.list_revisions(alone.id).unwrap()[0].name, "A. Smith");
+        assert!(db.list_revisions(untouched).unwrap().is_empty());
+        assert_eq!(db.get_talk(untouched).unwrap().updated_at, time(18));
+    }
+
+    fn found(db: &DBManager, input: &str) -> Vec<i32> {
+        db.search_talks(input, None, None, None, None).unwrap().iter().map(|result| result.talk.id).collect()
+    }
+
+    #[test]
+    fn search_follows_edits_to_talks() {
+        let db = memory_db();
+        let talk = add_talk(&db, &["Alice"], 1, "Fighting the borrow checker", TalkStatus::Scheduled);
+        assert_eq!(found(&db, "borrow"), vec![talk]);
+        assert_eq!(found(&db, "alice"), vec![talk]);
+
+        let results = db.search_talks("check", None, None, None, None).unwrap();
+        assert_eq!(results[0].snippet_html, "Fighting the borrow <mark>checker</mark>");
+
+        let description = String::from("Async runtimes compared");
+        let changes = UpdateTalk { name: None, talk_type: None, description: Some(&description), duration: None };
+        db.update_talk(talk, changes, None, None, None, time(19)).unwrap();
+        assert!(found(&db, "borrow").is_empty());
+        assert_eq!(found(&db, "runtime"), vec![talk]);
+    }
+
+    #[test]
+    fn search_leaves_out_unlisted_and_withdrawn_talks() {
+        let db = memory_db();
+        let scheduled = add_talk(&db, &["Alice"], 1, "Rust on microcontrollers", TalkStatus::Scheduled);
+        add_talk(&db, &["Mallory"], 1, "Rust for spammers", TalkStatus::Proposed);
+        add_talk(&db, &["Bob"], 1, "Rust again", TalkStatus::Withdrawn);
+        let presented = add_talk(&db, &["Carol"], 1, "Rust in production", TalkStatus::Presented);
+
+        let mut ids = found(&db, "rust");
+        ids.sort_unstable();
+        assert_eq!(ids, vec![scheduled, presented]);
+    }
+
+    #[test]
+    fn only_proposed_talks_are_moderated() {
+        let db = memory_db();
+        let names = vec![String::from("Mallory")];
+        let presenters = db.find_or_create_presenters(&names).unwrap();
+        let (name, description) = (String::from("Mallory"), String::new());
+        let talk = CreateTalk { name: &name, talk_type: 1, description: &description, status: TalkStatus::Proposed, duration: None, submitted_by: Some("visitor"), created_at: time(18), updated_at: time(18) };
+        let proposed = db.create_talk(talk, &presenters, &[], &[]).unwrap();
+
+        // The visitor who proposed the talk is returned to be told
+        assert_eq!(db.moderate_talk(proposed, TalkStatus::Scheduled, time(19)).unwrap().as_deref(), Some("visitor"));
+        let talk = db.get_talk(proposed).unwrap();
+        assert_eq!((talk.status, talk.updated_at), (TalkStatus::Scheduled, time(19)));
+        assert!(db.moderate_talk(proposed, TalkStatus::Rejected, time(20)).is_err());
+
+        let scheduled = add_talk(&db, &["Alice"], 1, "", TalkStatus::Scheduled);
+        assert!(db.moderate_talk(scheduled, TalkStatus::Rejected, time(20)).is_err());
+        assert_eq!(db.get_talk(scheduled).unwrap().status, TalkStatus::Scheduled);
+    }
 }
\ No newline at end of file
@@ -6,6 +6,7 @@ pub enum ErrorType {
     NotFound,
     Internal,
     BadRequest,
+    Forbidden,
 }
 
 #[derive(Debug)]
@@ -22,12 +23,12 @@ impl AppError {
         }
     }
 
-    #[allow(dead_code)]
     pub fn to_http_status(&self) -> warp::http::StatusCode {
         match self.err_type {
             ErrorType::NotFound => warp::http::StatusCode::NOT_FOUND,
             ErrorType::Internal => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
             ErrorType::BadRequest => warp::http::StatusCode::BAD_REQUEST,
+            ErrorType::Forbidden => warp::http::StatusCode::FORBIDDEN,
         }
     }
 
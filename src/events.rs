@@ -1,25 +1,195 @@
+use chrono::{Local, NaiveDateTime, SubsecRound};
 use futures::SinkExt;
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
+use url::Url;
 use warp::ws::Message;
 
-use crate::{Clients, db::DBManager, model::{CreateTalk, TalkType}};
+use crate::{Clients, client::Client, config::Config, db::DBManager, error::{AppError, ErrorType}, markdown, model::{CreateComment, CreateTalk, CreateTalkType, Link, Presented, Presenter, Talk, TalkStatus, TalkType, UpdateTalk, UpdateTalkType}, presenters::joined_names, tags::{self, tidy_tag}, timer::SharedTimer, validation::{self, Limits}};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+// Longest a single talk may be planned for, in minutes
+const MAX_DURATION: i32 = 240;
+
+// Most a timer may be extended by at once, in seconds
+const MAX_EXTENSION: i64 = 60 * 60;
+
+// Color of new talk types when none is given
+const DEFAULT_TYPE_COLOR: &str = "#78909c";
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(tag = "event")]
 pub enum EventRequest {
-    Create { name: String, talk_type: TalkType, desc: String },
+    // `presenters` lists every presenter, `name` is enough for a single presenter.
+    // Talks created by clients that are not authenticated wait for a moderator.
+    Create {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        presenters: Vec<String>,
+        // Slug or label of a talk type, e.g. "LightningTalk" or "lightning talk"
+        talk_type: String,
+        desc: String,
+        // Expected minutes, the default for the talk type when left out
+        duration: Option<i32>,
+        // Slides, repositories and videos, http and https only
+        #[serde(default)]
+        links: Vec<Link>,
+        // Topics of the talk, e.g. ["rust", "hardware"]
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    // `links` and `tags` replace every link and tag of the talk
+    Update { id: i32, name: Option<String>, presenters: Option<Vec<String>>, talk_type: Option<String>, desc: Option<String>, duration: Option<i32>, links: Option<Vec<Link>>, tags: Option<Vec<String>> },
     Hide { id: i32 },
+    // Only admins may put a talk back the way it was at one of its revisions
+    Revert { id: i32, revision: i32 },
+    // Authenticated clients may withdraw talks, admins also schedule and reject them
+    SetStatus { id: i32, status: TalkStatus },
+    // Only admins moderate proposed talks, `reason` is passed on to the visitor who proposed it
+    Approve { id: i32 },
+    Reject { id: i32, reason: Option<String> },
+    // Every client may vote for each forum topic once
+    Vote { id: i32 },
+    // Comments are sent to the clients watching the talk, only admins may hide them
+    Comment { talk_id: i32, author: String, body: String },
+    HideComment { id: i32 },
+    Watch { id: i32 },
+    // Only admins may merge presenters
+    MergePresenters { from: i32, into: i32 },
+    // Only admins manage talk types, a type can only be deleted while no talk has it
+    CreateTalkType {
+        slug: String,
+        label: String,
+        // The label when left out
+        heading: Option<String>,
+        // After the last type when left out
+        position: Option<i32>,
+        color: Option<String>,
+        duration: i32,
+        #[serde(default)]
+        accepts_votes: bool,
+        #[serde(default)]
+        after_meeting: bool,
+    },
+    UpdateTalkType { id: i32, slug: Option<String>, label: Option<String>, heading: Option<String>, position: Option<i32>, color: Option<String>, duration: Option<i32>, accepts_votes: Option<bool>, after_meeting: Option<bool> },
+    DeleteTalkType { id: i32 },
+    // Admins moderate the meeting, presenting the visible talks in the running order
+    Start,
+    Next,
+    Previous,
+    // Admins time the talk being presented, moving to another talk restarts the timer
+    StartTimer,
+    PauseTimer,
+    ExtendTimer { seconds: i64 },
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(tag = "event")]
 pub enum EventResponse {
-    Show { id: i32, name: String, talk_type: TalkType, description: String },
+    // `description_html` is the markdown description rendered and sanitized, safe to insert as html,
+    // like Talk the label is sent as `talk_type` and the whole talk type as `type`, and `votes` is the current count
+    Show { id: i32, name: String, #[serde(rename = "talk_type")] type_label: String, #[serde(rename = "type")] talk_type: TalkType, description: String, description_html: String, duration: Option<i32>, presenters: Vec<Presenter>, links: Vec<Link>, tags: Vec<String>, votes: i32 },
+    Update { id: i32, name: String, #[serde(rename = "talk_type")] type_label: String, #[serde(rename = "type")] talk_type: TalkType, description: String, description_html: String, duration: Option<i32>, presenters: Vec<Presenter>, links: Vec<Link>, tags: Vec<String>, votes: i32 },
     Hide { id: i32 },
+    // The talk moved from status `from` to `status`
+    Status { id: i32, from: TalkStatus, status: TalkStatus },
+    // A talk is waiting for a moderator, only sent to admins and the visitor who proposed it
+    Proposed {
+        talk: Talk,
+        #[serde(skip)]
+        submitted_by: Option<String>,
+    },
+    // A proposed talk was turned down, only sent to admins and the visitor who proposed it
+    Rejected {
+        id: i32,
+        reason: Option<String>,
+        #[serde(skip)]
+        submitted_by: Option<String>,
+    },
+    // The talk now has `votes` votes
+    Vote { id: i32, votes: i32 },
+    // Only sent to the clients watching talk `talk_id`
+    Comment { id: i32, talk_id: i32, author: String, body: String, created_at: NaiveDateTime },
+    HideComment { id: i32, talk_id: i32 },
+    // A slide file was uploaded for talk `talk_id`, only sent to its watchers
+    Attachment { id: i32, talk_id: i32, file_name: String, content_type: String, size: i64 },
+    // Every talk type in the order talks are presented, sent whenever a type changes
+    TalkTypes { talk_types: Vec<TalkType> },
+    // The talks of presenter `from` now belong to `into`, named `name`
+    MergePresenters { from: i32, into: i32, name: String, talks: Vec<Talk> },
+    // The talk being presented and the talks that are done
+    Presenting { current: Option<i32>, presented: Vec<Presented> },
+    // Seconds left for the talk being presented, sent every second while the timer runs
+    Timer { talk: Option<i32>, length: i64, remaining: i64, running: bool },
+    // Sent once when the talk runs out of time
+    Overrun { talk: i32 },
     Authenticate { authenticated: bool },
+    // Why a request failed, with the http status it would have had, only sent to the client that made it
+    Error { status: u16, error: String },
     NOP,
 }
 
+impl EventResponse {
+    // The talk this response is about
+    pub fn talk_id(&self) -> Option<i32> {
+        match *self {
+            EventResponse::Show { id, .. } => Some(id),
+            EventResponse::Update { id, .. } => Some(id),
+            EventResponse::Hide { id } => Some(id),
+            EventResponse::Status { id, .. } => Some(id),
+            EventResponse::Proposed { ref talk, .. } => Some(talk.id),
+            EventResponse::Rejected { id, .. } => Some(id),
+            EventResponse::Vote { id, .. } => Some(id),
+            EventResponse::Comment { talk_id, .. } => Some(talk_id),
+            EventResponse::HideComment { talk_id, .. } => Some(talk_id),
+            EventResponse::Attachment { talk_id, .. } => Some(talk_id),
+            EventResponse::TalkTypes { .. } => None,
+            EventResponse::MergePresenters { .. } => None,
+            EventResponse::Presenting { current, .. } => current,
+            EventResponse::Timer { talk, .. } => talk,
+            EventResponse::Overrun { talk } => Some(talk),
+            EventResponse::Authenticate { .. } => None,
+            EventResponse::Error { .. } => None,
+            EventResponse::NOP => None,
+        }
+    }
+
+    // The talk whose watchers are the only clients sent this response
+    pub fn watched_talk(&self) -> Option<i32> {
+        match *self {
+            EventResponse::Comment { talk_id, .. } => Some(talk_id),
+            EventResponse::HideComment { talk_id, .. } => Some(talk_id),
+            EventResponse::Attachment { talk_id, .. } => Some(talk_id),
+            _ => None,
+        }
+    }
+
+    // Whether only admins and the visitor who proposed the talk are sent this response
+    pub fn is_moderation(&self) -> bool {
+        matches!(self, EventResponse::Proposed { .. } | EventResponse::Rejected { .. })
+    }
+
+    // Client id of the visitor who proposed the talk of a moderation response
+    pub fn submitted_by(&self) -> Option<&str> {
+        match self {
+            EventResponse::Proposed { submitted_by, .. } => submitted_by.as_deref(),
+            EventResponse::Rejected { submitted_by, .. } => submitted_by.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+// Tells a single client why its request was refused
+pub async fn send_error(clients: &Clients, id: &str, err: &AppError) {
+    let event = EventResponse::Error { status: err.to_http_status().as_u16(), error: err.message.clone() };
+
+    if let Ok(str) = serde_json::to_string(&event) {
+        if let Some(sender) = clients.write().await.get_mut(id).and_then(|client| client.sender.as_mut()) {
+            let _ = sender.send(Ok(Message::text(&str))).await;
+        }
+    }
+}
+
 pub async fn send_events(clients: Clients, event: EventResponse) {
     // Don't bother sending NOPs
     if event == EventResponse::NOP {
@@ -31,8 +201,15 @@ pub async fn send_events(clients: Clients, event: EventResponse) {
         // Wrap the string as a text message
         let msg = Message::text(&str);
 
-        // Send the message to all clients
+        // Send the message to all clients, or only those watching the talk
         for (_, client) in clients.write().await.iter_mut() {
+            if event.watched_talk().is_some_and(|talk| client.watching != Some(talk)) {
+                continue;
+            }
+            if event.is_moderation() && !client.admin && event.submitted_by() != Some(client.id.as_str()) {
+                continue;
+            }
+
             if let Some(sender) = &mut client.sender {
                 let m = Ok(msg.clone());
                 let _ = sender.send(m).await;
@@ -42,34 +219,491 @@ pub async fn send_events(clients: Clients, event: EventResponse) {
 }
 
 // Process a request and return a response
-pub fn process_event(event: EventRequest, db: &DBManager) -> EventResponse {
+pub fn process_event(event: EventRequest, db: &DBManager, client: &Client, config: &Config, timer: &SharedTimer) -> Result<EventResponse, AppError> {
+    // Only authenticated clients may change talks, the others may propose new ones
+    if !client.authenticated && !matches!(event, EventRequest::Create { .. }) {
+        return Err(AppError::new("client is not authenticated", ErrorType::Forbidden));
+    }
+
     match event {
-        EventRequest::Create { name, talk_type, desc } => {
-            // Add talk to the database            
-            let talk: CreateTalk = CreateTalk { name: &name, talk_type: talk_type, description: &desc, is_visible: true };
+        EventRequest::Create { name, presenters, talk_type, desc, duration, links, tags } => {
+            let names = check_names(if presenters.is_empty() { vec![name] } else { presenters }, &config.limits)?;
+            let desc = validation::text("the description", &desc, config.limits.description)?;
+            check_duration(duration)?;
+            let links = check_links(links, &config.limits)?;
+            let tags = check_tags(tags, &config.limits)?;
+            let talk_type = db.find_talk_type(&talk_type)?;
+            let presenters = find_presenters(db, &names)?;
+            let name = joined_names(&presenters);
+
+            // Add talk to the database, talks of visitors wait for a moderator
+            let (status, submitted_by) = if client.authenticated {
+                (TalkStatus::Scheduled, None)
+            } else {
+                (TalkStatus::Proposed, Some(client.id.as_str()).filter(|id| !id.is_empty()))
+            };
+            let created = now();
+            let talk: CreateTalk = CreateTalk { name: &name, talk_type: talk_type.id, description: &desc, status, duration, submitted_by, created_at: created, updated_at: created };
+            let id = db.create_talk(talk, &presenters, &links, &tags)?;
+
+            if status == TalkStatus::Proposed {
+                return Ok(EventResponse::Proposed { talk: db.get_talk(id)?, submitted_by: submitted_by.map(String::from) });
+            }
 
             // Return data
-            match db.create_talk(talk) {
-                Ok(id) => {
-                    EventResponse::Show { id, name, talk_type, description: desc }
-                }
-                Err(_) => {
-                    EventResponse::NOP
-                }
+            Ok(EventResponse::Show { id, name, type_label: talk_type.label.clone(), talk_type, description_html: markdown::render(&desc), description: desc, duration, presenters, links, tags, votes: 0 })
+        }
+        EventRequest::Update { id, name, presenters, talk_type, desc, duration, links, tags } => {
+            if name.is_none() && presenters.is_none() && talk_type.is_none() && desc.is_none() && duration.is_none() && links.is_none() && tags.is_none() {
+                return Err(AppError::new(&format!("nothing to update on talk {}", id), ErrorType::BadRequest));
+            }
+            listed_talk(db, client, id)?;
+            let names = presenters.or_else(|| name.map(|name| vec![name]))
+                .map(|names| check_names(names, &config.limits))
+                .transpose()?;
+            let desc = desc.map(|desc| validation::text("the description", &desc, config.limits.description)).transpose()?;
+            check_duration(duration)?;
+            let links = links.map(|links| check_links(links, &config.limits)).transpose()?;
+            let tags = tags.map(|tags| check_tags(tags, &config.limits)).transpose()?;
+            let talk_type = match talk_type {
+                Some(talk_type) => Some(db.find_talk_type(&talk_type)?.id),
+                None => None,
+            };
+
+            let presenters = match names {
+                Some(names) => Some(find_presenters(db, &names)?),
+                None => None,
+            };
+            let name = presenters.as_deref().map(joined_names);
+
+            // Update the talk in the database
+            let changes = UpdateTalk { name: name.as_ref(), talk_type, description: desc.as_ref(), duration };
+            if db.update_talk(id, changes, presenters.as_deref(), links.as_deref(), tags.as_deref(), now())? == 0 {
+                return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound));
             }
+
+            updated(db, id)
+        }
+        EventRequest::Revert { id, revision } => {
+            require_admin(client, "revert talks")?;
+            let revision = db.get_revision(revision)?;
+            if revision.talk_id != id {
+                return Err(AppError::new(&format!("talk {} has no revision {}", id, revision.id), ErrorType::NotFound));
+            }
+            if db.list_talk_types()?.iter().all(|talk_type| talk_type.id != revision.talk_type) {
+                return Err(AppError::new(&format!("the talk type of revision {} was deleted", revision.id), ErrorType::BadRequest));
+            }
+            let presenters = find_presenters(db, &revision.presenters)?;
+
+            if db.revert_talk(id, &revision, &presenters, now())? == 0 {
+                return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound));
+            }
+
+            updated(db, id)
         }
         EventRequest::Hide { id } => {
             // Update the talk in the database
-            let res = db.hide_talk(id);
+            if db.hide_talk(id, now())? == 0 {
+                return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound));
+            }
 
-            match res {
-                Ok(_) => {
-                    EventResponse::Hide { id }
-                }
-                Err(_) => {
-                    EventResponse::NOP
+            Ok(EventResponse::Hide { id })
+        }
+        EventRequest::SetStatus { id, status } => {
+            match status {
+                TalkStatus::Withdrawn => {}
+                TalkStatus::Scheduled | TalkStatus::Rejected => { require_admin(client, &format!("make talks {}", status))?; }
+                _ => {
+                    return Err(AppError::new(&format!("talks become {} with Start, Next and Previous", status), ErrorType::BadRequest));
                 }
             }
+
+            let from = db.set_status(id, status, now())?;
+            Ok(EventResponse::Status { id, from, status })
+        }
+        EventRequest::Approve { id } => {
+            require_admin(client, "approve talks")?;
+            db.moderate_talk(id, TalkStatus::Scheduled, now())?;
+
+            // Every client shows the talk now that it is scheduled
+            let talk = db.get_talk(id)?;
+            Ok(EventResponse::Show { id, name: talk.name, type_label: talk.type_label, talk_type: talk.talk_type, description: talk.description, description_html: talk.description_html, duration: talk.duration, presenters: talk.presenters, links: talk.links, tags: talk.tags, votes: talk.votes })
+        }
+        EventRequest::Reject { id, reason } => {
+            require_admin(client, "reject talks")?;
+            let reason = validation::optional_text("the reason", reason, config.limits.reason)?;
+
+            let submitted_by = db.moderate_talk(id, TalkStatus::Rejected, now())?;
+            Ok(EventResponse::Rejected { id, reason, submitted_by })
+        }
+        EventRequest::Vote { id } => {
+            let talk = db.get_talk(id)?;
+            if !talk.accepts_votes() {
+                return Err(AppError::new(&format!("{}s cannot be voted for", talk.talk_type), ErrorType::BadRequest));
+            }
+            if talk.status != TalkStatus::Scheduled {
+                return Err(AppError::new(&format!("talk {} is no longer up for discussion", id), ErrorType::BadRequest));
+            }
+
+            match db.vote(id, &client.id, now())? {
+                Some(votes) => Ok(EventResponse::Vote { id, votes }),
+                None => Err(AppError::new(&format!("already voted for talk {}", id), ErrorType::BadRequest)),
+            }
+        }
+        EventRequest::Comment { talk_id, author, body } => {
+            let author = validation::line("your name", &author, config.limits.name)?;
+            let body = validation::text("the comment", &body, config.limits.comment)?;
+
+            // Past talks may be commented on as well
+            listed_talk(db, client, talk_id)?;
+            let comment = db.create_comment(CreateComment { talk_id, author: &author, body: &body, created_at: now() })?;
+            Ok(EventResponse::Comment { id: comment.id, talk_id, author: comment.author, body: comment.body, created_at: comment.created_at })
+        }
+        EventRequest::HideComment { id } => {
+            require_admin(client, "hide comments")?;
+            let comment = db.get_comment(id)?;
+            db.hide_comment(id)?;
+
+            Ok(EventResponse::HideComment { id, talk_id: comment.talk_id })
+        }
+        EventRequest::Watch { .. } => {
+            // Watching a talk only changes what the client is sent, see client_connection
+            Ok(EventResponse::NOP)
+        }
+        EventRequest::MergePresenters { from, into } => {
+            require_admin(client, "merge presenters")?;
+            if from == into {
+                return Err(AppError::new(&format!("cannot merge presenter {} into itself", from), ErrorType::BadRequest));
+            }
+
+            let (presenter, talks) = db.merge_presenters(from, into, now())?;
+            Ok(EventResponse::MergePresenters { from, into, name: presenter.name, talks })
+        }
+        EventRequest::CreateTalkType { slug, label, heading, position, color, duration, accepts_votes, after_meeting } => {
+            require_admin(client, "manage talk types")?;
+            let heading = heading.unwrap_or_else(|| label.clone());
+            let color = color.unwrap_or_else(|| DEFAULT_TYPE_COLOR.to_string());
+            let slug = validation::line("the slug", &slug, config.limits.type_name)?;
+            let label = validation::line("the label", &label, config.limits.type_name)?;
+            let heading = validation::line("the heading", &heading, config.limits.type_name)?;
+            check_talk_type(Some(&slug), Some(&color), Some(duration))?;
+
+            let position = match position {
+                Some(position) => position,
+                None => db.list_talk_types()?.last().map_or(1, |last| last.position + 1),
+            };
+            let talk_type = CreateTalkType {
+                slug: &slug,
+                label: &label,
+                heading: &heading,
+                position,
+                color: &color,
+                duration,
+                accepts_votes,
+                after_meeting,
+            };
+            db.create_talk_type(talk_type)?;
+
+            Ok(EventResponse::TalkTypes { talk_types: db.list_talk_types()? })
+        }
+        EventRequest::UpdateTalkType { id, slug, label, heading, position, color, duration, accepts_votes, after_meeting } => {
+            require_admin(client, "manage talk types")?;
+            if slug.is_none() && label.is_none() && heading.is_none() && position.is_none() && color.is_none() && duration.is_none() && accepts_votes.is_none() && after_meeting.is_none() {
+                return Err(AppError::new(&format!("nothing to update on talk type {}", id), ErrorType::BadRequest));
+            }
+            let slug = slug.map(|slug| validation::line("the slug", &slug, config.limits.type_name)).transpose()?;
+            let label = label.map(|label| validation::line("the label", &label, config.limits.type_name)).transpose()?;
+            let heading = heading.map(|heading| validation::line("the heading", &heading, config.limits.type_name)).transpose()?;
+            check_talk_type(slug.as_deref(), color.as_deref(), duration)?;
+
+            let changes = UpdateTalkType {
+                slug: slug.as_deref(),
+                label: label.as_deref(),
+                heading: heading.as_deref(),
+                position,
+                color: color.as_deref(),
+                duration,
+                accepts_votes,
+                after_meeting,
+            };
+            if db.update_talk_type(id, changes)? == 0 {
+                return Err(AppError::new(&format!("talk type {} does not exist", id), ErrorType::NotFound));
+            }
+
+            Ok(EventResponse::TalkTypes { talk_types: db.list_talk_types()? })
+        }
+        EventRequest::DeleteTalkType { id } => {
+            require_admin(client, "manage talk types")?;
+            if db.delete_talk_type(id)? == 0 {
+                return Err(AppError::new(&format!("talk type {} does not exist", id), ErrorType::NotFound));
+            }
+
+            Ok(EventResponse::TalkTypes { talk_types: db.list_talk_types()? })
+        }
+        EventRequest::Start => {
+            require_admin(client, "start the meeting")?;
+            db.present_next(false, config.sort_by_votes, now())?;
+            restart_timer(db, timer)?;
+            presenting(db)
+        }
+        EventRequest::Next => {
+            require_admin(client, "move to the next talk")?;
+            db.present_next(true, config.sort_by_votes, now())?;
+            restart_timer(db, timer)?;
+            presenting(db)
+        }
+        EventRequest::Previous => {
+            require_admin(client, "move to the previous talk")?;
+            db.present_previous(now())?;
+            restart_timer(db, timer)?;
+            presenting(db)
+        }
+        EventRequest::StartTimer => {
+            require_admin(client, "start the timer")?;
+            let (current, _) = db.presenting()?;
+            let current = current.ok_or_else(|| AppError::new("no talk is being presented", ErrorType::BadRequest))?;
+
+            let mut timer = timer.lock().unwrap();
+            if timer.talk() != Some(current) {
+                timer.reset(Some(&db.get_talk(current)?));
+            }
+            timer.start();
+            Ok(timer.response())
+        }
+        EventRequest::PauseTimer => {
+            require_admin(client, "pause the timer")?;
+            let mut timer = timer.lock().unwrap();
+            timer.pause();
+            Ok(timer.response())
+        }
+        EventRequest::ExtendTimer { seconds } => {
+            require_admin(client, "extend the timer")?;
+            if !(1..=MAX_EXTENSION).contains(&seconds) {
+                return Err(AppError::new(&format!("a timer can be extended by 1 to {} seconds", MAX_EXTENSION), ErrorType::BadRequest));
+            }
+
+            let mut timer = timer.lock().unwrap();
+            if timer.talk().is_none() {
+                return Err(AppError::new("no talk is being timed", ErrorType::BadRequest));
+            }
+            timer.extend(seconds as u64);
+            Ok(timer.response())
+        }
+    }
+}
+
+// Talks are timed to the second
+fn now() -> NaiveDateTime {
+    Local::now().naive_local().trunc_subsecs(0)
+}
+
+fn require_admin(client: &Client, action: &str) -> Result<(), AppError> {
+    if client.admin {
+        Ok(())
+    } else {
+        Err(AppError::new(&format!("only admins may {}", action), ErrorType::Forbidden))
+    }
+}
+
+// Proposed and rejected talks do not exist for anyone but admins
+pub fn listed_talk(db: &DBManager, client: &Client, id: i32) -> Result<Talk, AppError> {
+    let talk = db.get_talk(id)?;
+    if !talk.status.is_listed() && !client.admin {
+        return Err(AppError::new(&format!("talk {} does not exist", id), ErrorType::NotFound));
+    }
+
+    Ok(talk)
+}
+
+// The edited talk, changes to proposed talks only go to admins and those to rejected talks to no one
+fn updated(db: &DBManager, id: i32) -> Result<EventResponse, AppError> {
+    let talk: Talk = db.get_talk(id)?;
+    match talk.status {
+        TalkStatus::Proposed => Ok(EventResponse::Proposed { talk, submitted_by: None }),
+        TalkStatus::Rejected => Ok(EventResponse::NOP),
+        _ => Ok(EventResponse::Update { id, name: talk.name, type_label: talk.type_label, talk_type: talk.talk_type, description: talk.description, description_html: talk.description_html, duration: talk.duration, presenters: talk.presenters, links: talk.links, tags: talk.tags, votes: talk.votes }),
+    }
+}
+
+fn presenting(db: &DBManager) -> Result<EventResponse, AppError> {
+    let (current, presented) = db.presenting()?;
+    Ok(EventResponse::Presenting { current, presented })
+}
+
+// Times the talk being presented from the start when it changed
+fn restart_timer(db: &DBManager, timer: &SharedTimer) -> Result<(), AppError> {
+    let (current, _) = db.presenting()?;
+    if timer.lock().unwrap().talk() == current {
+        return Ok(());
+    }
+
+    let talk = match current {
+        Some(id) => Some(db.get_talk(id)?),
+        None => None,
+    };
+    timer.lock().unwrap().reset(talk.as_ref());
+    Ok(())
+}
+
+// Presenters matching names ignore case, so talks by the same person share a presenter
+fn find_presenters(db: &DBManager, names: &[String]) -> Result<Vec<Presenter>, AppError> {
+    let presenters = db.find_or_create_presenters(names)?;
+    if presenters.is_empty() {
+        return Err(AppError::new("a talk needs at least one presenter", ErrorType::BadRequest));
+    }
+
+    Ok(presenters)
+}
+
+// Every presenter needs a name, names are normalized the way they are stored
+fn check_names(names: Vec<String>, limits: &Limits) -> Result<Vec<String>, AppError> {
+    names.iter()
+        .map(|name| validation::line("presenter names", name, limits.name))
+        .collect()
+}
+
+// Talks take at least a minute and no longer than a meeting
+fn check_duration(duration: Option<i32>) -> Result<(), AppError> {
+    match duration {
+        Some(minutes) if !(1..=MAX_DURATION).contains(&minutes) => {
+            Err(AppError::new(&format!("duration must be between 1 and {} minutes", MAX_DURATION), ErrorType::BadRequest))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Slugs are used in urls and requests, colors end up in style attributes
+fn check_talk_type(slug: Option<&str>, color: Option<&str>, duration: Option<i32>) -> Result<(), AppError> {
+    if let Some(slug) = slug {
+        if !slug.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(AppError::new("talk type slugs may only contain letters, digits, dashes or underscores", ErrorType::BadRequest));
+        }
+    }
+    if let Some(color) = color {
+        let hex = color.strip_prefix('#').unwrap_or_default();
+        if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AppError::new(&format!("{} is not a color like #78909c", color), ErrorType::BadRequest));
         }
     }
+
+    check_duration(duration)
+}
+
+// Tags are stored tidied, sorted and without repeats, see tidy_tag
+fn check_tags(tags: Vec<String>, limits: &Limits) -> Result<Vec<String>, AppError> {
+    let mut tidy: Vec<String> = tags.iter().map(|tag| tidy_tag(tag)).filter(|tag| !tag.is_empty()).collect();
+    tidy.sort();
+    tidy.dedup();
+
+    validation::count("tags", &tidy, limits.tags)?;
+    tidy.into_iter()
+        .map(|tag| {
+            let tag = validation::line("a tag", &tag, limits.tag)?;
+            if !tags::is_valid(&tag) {
+                return Err(AppError::new(&format!("{} is not a valid tag, use letters, digits, -, + and .", tag), ErrorType::BadRequest));
+            }
+
+            Ok(tag)
+        })
+        .collect()
+}
+
+// Links must be absolute http or https urls, they are stored the way they are parsed
+fn check_links(links: Vec<Link>, limits: &Limits) -> Result<Vec<Link>, AppError> {
+    validation::count("links", &links, limits.links)?;
+
+    links.into_iter()
+        .map(|link| {
+            let invalid = || AppError::new(&format!("{} is not a valid http or https link", link.url), ErrorType::BadRequest);
+            let url = validation::line("a link", &link.url, limits.url)?;
+
+            let url = Url::parse(&url).map_err(|_| invalid())?;
+            if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+                return Err(invalid());
+            }
+
+            Ok(Link { kind: link.kind, url: url.to_string() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{client::Client, config::Config, db::{DBManager, tests::{add_talk, memory_db}}, error::AppError, model::TalkStatus, timer::create_timer};
+    use super::{EventRequest, EventResponse, listed_talk, process_event};
+
+    fn client(id: &str, authenticated: bool, admin: bool) -> Client {
+        Client { id: id.to_string(), sender: None, authenticated, admin, watching: None, second_chance: false }
+    }
+
+    fn process(db: &DBManager, client: &Client, event: EventRequest) -> Result<EventResponse, AppError> {
+        process_event(event, db, client, &Config::from_env(), &create_timer())
+    }
+
+    fn status<T>(result: Result<T, AppError>) -> u16 {
+        match result {
+            Ok(_) => 200,
+            Err(err) => err.to_http_status().as_u16(),
+        }
+    }
+
+    #[test]
+    fn unlisted_talks_are_only_found_by_admins() {
+        let db = memory_db();
+        let (visitor, member, admin) = (client("visitor", false, false), client("member", true, false), client("admin", true, true));
+
+        for &unlisted in &[TalkStatus::Proposed, TalkStatus::Rejected] {
+            let talk = add_talk(&db, &["Mallory"], 1, "", unlisted);
+            assert_eq!(status(listed_talk(&db, &visitor, talk)), 404);
+            assert_eq!(status(listed_talk(&db, &member, talk)), 404);
+            assert_eq!(status(listed_talk(&db, &admin, talk)), 200);
+
+            let comment = || EventRequest::Comment { talk_id: talk, author: String::from("Bob"), body: String::from("Nice") };
+            assert_eq!(status(process(&db, &member, comment())), 404);
+            assert_eq!(status(process(&db, &admin, comment())), 200);
+        }
+
+        let talk = add_talk(&db, &["Alice"], 1, "", TalkStatus::Scheduled);
+        assert_eq!(status(listed_talk(&db, &visitor, talk)), 200);
+    }
+
+    fn propose(db: &DBManager) -> i32 {
+        let event = EventRequest::Create { name: String::from("Mallory"), presenters: Vec::new(), talk_type: String::from("LightningTalk"), desc: String::from("Rust"), duration: None, links: Vec::new(), tags: Vec::new() };
+        match process(db, &client("visitor", false, false), event).unwrap() {
+            EventResponse::Proposed { talk, submitted_by } => {
+                assert_eq!(submitted_by.as_deref(), Some("visitor"));
+                talk.id
+            }
+            other => panic!("expected Proposed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn approving_shows_the_talk_to_everyone() {
+        let db = memory_db();
+        let talk = propose(&db);
+
+        assert_eq!(status(process(&db, &client("member", true, false), EventRequest::Approve { id: talk })), 403);
+
+        let response = process(&db, &client("admin", true, true), EventRequest::Approve { id: talk }).unwrap();
+        assert!(matches!(response, EventResponse::Show { id, .. } if id == talk));
+        assert!(!response.is_moderation());
+        assert_eq!(db.get_talk(talk).unwrap().status, TalkStatus::Scheduled);
+
+        // Only proposed talks are moderated
+        assert_eq!(status(process(&db, &client("admin", true, true), EventRequest::Approve { id: talk })), 400);
+    }
+
+    #[test]
+    fn rejecting_tells_the_visitor_why() {
+        let db = memory_db();
+        let talk = propose(&db);
+
+        let reject = EventRequest::Reject { id: talk, reason: Some(String::from(" Off topic ")) };
+        let response = process(&db, &client("admin", true, true), reject).unwrap();
+        assert!(response.is_moderation());
+        assert_eq!(response.submitted_by(), Some("visitor"));
+        assert!(matches!(response, EventResponse::Rejected { id, reason: Some(ref reason), .. } if id == talk && reason == "Off topic"));
+        assert_eq!(db.get_talk(talk).unwrap().status, TalkStatus::Rejected);
+    }
 }
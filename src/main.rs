@@ -1,19 +1,37 @@
+// The openapi document is one large json! invocation
+#![recursion_limit = "256"]
+
 #[macro_use]
 extern crate diesel;
 
-use std::{convert::Infallible, env};
+use std::{convert::Infallible, env, sync::Arc};
+use config::{Config, SharedConfig};
 use db::DBManager;
 use diesel::{SqliteConnection, r2d2::{ConnectionManager, Pool}};
 use error::{AppError, ErrorType};
+use model::UpdateTalkType;
+use serde::de::DeserializeOwned;
 use warp::{Filter, hyper::Uri, reject};
 
-use crate::client::{Clients, create_clients, garabage_collector};
+use crate::{client::{Clients, create_clients, garabage_collector}, timer::{SharedTimer, create_timer, run_timer}};
 
+mod agenda;
 mod api;
+mod attachments;
+mod config;
 mod events;
 mod db;
 mod error;
+mod markdown;
+mod minutes;
 mod model;
+mod openapi;
+mod presenters;
+mod revisions;
+mod stats;
+mod tags;
+mod timer;
+mod validation;
 mod client;
 pub mod schema;
 
@@ -48,18 +66,121 @@ async fn main() {
         }
     };
 
+    let config: SharedConfig = Arc::new(Config::from_env());
+
     let pool = sqlite_pool(&database_url);
+    set_talk_durations(&pool, &config.talk_durations);
+
+    // Timer for the talk being presented
+    let timer: SharedTimer = create_timer();
 
     // index welcome route
     let welcome_route = warp::path::end()
+        .and(with_config(config.clone()))
         .and(with_db_access_manager(pool.clone()))
         .and_then(api::welcome_handler);
 
+    // projector view of the running order
+    let present_route = warp::path("present")
+        .and(warp::path::end())
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::present_handler);
+
     // show all talks in order by id (chronological order)
     let all_route = warp::path("all")
+        .and(warp::query())
         .and(with_db_access_manager(pool.clone()))
         .and_then(api::all_talks_handler);
 
+    // list of past meetings
+    let meetings_route = warp::path("meetings")
+        .and(warp::path::end())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::meetings_handler);
+
+    // talks presented at a past meeting, e.g. /meetings/2021-03-18
+    let meeting_route = warp::path("meetings")
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::meeting_handler);
+
+    // full-text search over every talk
+    let search_route = warp::path("search")
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::search_handler);
+
+    // statistics over every talk
+    let stats_route = warp::path("stats")
+        .and(warp::path::end())
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::stats_handler);
+
+    // everyone who has given a talk
+    let presenters_route = warp::path("presenters")
+        .and(warp::path::end())
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::presenters_handler);
+
+    // every tag in use
+    let tags_route = warp::path("tags")
+        .and(warp::path::end())
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::tags_handler);
+
+    // every talk with a tag, e.g. /tags/rust
+    let tag_route = warp::path!("tags" / String)
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::tag_handler);
+
+    // a talk and its comments, e.g. /talks/3
+    let talk_route = warp::path!("talks" / i32)
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::talk_handler);
+
+    // every earlier version of a talk, e.g. /talks/3/history
+    let history_route = warp::path!("talks" / i32 / "history")
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::history_handler);
+
+    // an uploaded slide file, e.g. /attachments/3
+    let attachment_route = warp::path!("attachments" / i32)
+        .and(warp::get())
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::download_attachment_handler);
+
+    // talk history of a presenter, e.g. /presenters/3
+    let presenter_route = warp::path!("presenters" / i32)
+        .and(warp::header::optional("accept"))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::presenter_handler);
+
+    // meeting minutes for the visible talks
+    let minutes_route = warp::path("minutes.md")
+        .and(warp::path::end())
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::minutes_handler);
+
+    // meeting minutes for a past meeting, e.g. /minutes/2021-03-18.md
+    let meeting_minutes_route = warp::path("minutes")
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::meeting_minutes_handler);
+
     // Indicates whether the service is up
     let health_route = warp::path("health")
         .and(with_clients(clients.clone()))
@@ -73,19 +194,269 @@ async fn main() {
 
     let authenticate = warp::path("authenticate")
         .and(warp::body::json())
+        .and(with_config(config.clone()))
         .and(with_clients(clients.clone()))
         .and_then(api::authenticate);
 
     // Gets talks route
     let talks = warp::path("talks")
+        .and(warp::path::end())
+        .and(with_config(config.clone()))
         .and(with_db_access_manager(pool.clone()))
         .and_then(api::visible_talks);
 
+    // Lists talks with filtering, sorting and paging
+    let api_talks = warp::path!("api" / "talks")
+        .and(warp::get())
+        .and(warp::query())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::list_talks_handler);
+
+    // Versioned REST api for talks, changes are broadcast like websocket events
+    let api_v1_list = warp::path!("api" / "v1" / "talks")
+        .and(warp::get())
+        .and(warp::query())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::list_talks_handler);
+
+    let api_v1_get = warp::path!("api" / "v1" / "talks" / i32)
+        .and(warp::get())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::get_talk_handler);
+
+    let api_v1_create = warp::path!("api" / "v1" / "talks")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::create_talk_handler);
+
+    let api_v1_update = warp::path!("api" / "v1" / "talks" / i32)
+        .and(warp::patch())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::update_talk_handler);
+
+    let api_v1_delete = warp::path!("api" / "v1" / "talks" / i32)
+        .and(warp::delete())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::delete_talk_handler);
+
+    let api_v1_status = warp::path!("api" / "v1" / "talks" / i32 / "status")
+        .and(warp::put())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::set_status_handler);
+
+    let api_v1_revisions = warp::path!("api" / "v1" / "talks" / i32 / "revisions")
+        .and(warp::get())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::revisions_handler);
+
+    let api_v1_revert = warp::path!("api" / "v1" / "talks" / i32 / "revisions" / i32 / "revert")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::revert_handler);
+
+    let api_v1_proposals = warp::path!("api" / "v1" / "proposals")
+        .and(warp::get())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::proposals_handler);
+
+    let api_v1_approve = warp::path!("api" / "v1" / "talks" / i32 / "approve")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::approve_handler);
+
+    let api_v1_reject = warp::path!("api" / "v1" / "talks" / i32 / "reject")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::reject_handler);
+
+    let api_v1_vote = warp::path!("api" / "v1" / "talks" / i32 / "votes")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::vote_handler);
+
+    let api_v1_comments = warp::path!("api" / "v1" / "talks" / i32 / "comments")
+        .and(warp::get())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::comments_handler);
+
+    let api_v1_comment = warp::path!("api" / "v1" / "talks" / i32 / "comments")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::create_comment_handler);
+
+    let api_v1_hide_comment = warp::path!("api" / "v1" / "comments" / i32)
+        .and(warp::delete())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::hide_comment_handler);
+
+    let api_v1_attachments = warp::path!("api" / "v1" / "talks" / i32 / "attachments")
+        .and(warp::get())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::attachments_handler);
+
+    // Leave room for the rest of the form around the largest allowed file
+    let api_v1_upload = warp::path!("api" / "v1" / "talks" / i32 / "attachments")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(warp::multipart::form().max_length(config.max_attachment_size + 64 * 1024))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::upload_attachment_handler);
+
+    let api_v1_talk_types = warp::path!("api" / "v1" / "talk-types")
+        .and(warp::get())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::talk_types_handler);
+
+    let api_v1_create_talk_type = warp::path!("api" / "v1" / "talk-types")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::create_talk_type_handler);
+
+    let api_v1_update_talk_type = warp::path!("api" / "v1" / "talk-types" / i32)
+        .and(warp::patch())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::update_talk_type_handler);
+
+    let api_v1_delete_talk_type = warp::path!("api" / "v1" / "talk-types" / i32)
+        .and(warp::delete())
+        .and(warp::header::optional("x-client-id"))
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::delete_talk_type_handler);
+
+    let api_v1_agenda = warp::path!("api" / "v1" / "agenda")
+        .and(warp::get())
+        .and(with_config(config.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::agenda_handler);
+
+    let api_v1_timer = warp::path!("api" / "v1" / "timer")
+        .and(warp::get())
+        .and(with_timer(timer.clone()))
+        .and_then(api::timer_handler);
+
+    let api_v1_suggest = warp::path!("api" / "v1" / "presenters" / "suggest")
+        .and(warp::get())
+        .and(warp::query())
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::suggest_presenters_handler);
+
+    let api_v1_merge = warp::path!("api" / "v1" / "presenters" / "merge")
+        .and(warp::post())
+        .and(warp::header::optional("x-client-id"))
+        .and(json_body())
+        .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
+        .and(with_db_access_manager(pool.clone()))
+        .and_then(api::merge_presenters_handler);
+
+    let api_v1 = api_v1_list
+        .or(api_v1_get)
+        .or(api_v1_create)
+        .or(api_v1_update)
+        .or(api_v1_delete)
+        .or(api_v1_status)
+        .or(api_v1_revisions)
+        .or(api_v1_revert)
+        .or(api_v1_proposals)
+        .or(api_v1_approve)
+        .or(api_v1_reject)
+        .or(api_v1_vote)
+        .or(api_v1_comments)
+        .or(api_v1_comment)
+        .or(api_v1_hide_comment)
+        .or(api_v1_attachments)
+        .or(api_v1_upload)
+        .or(api_v1_talk_types)
+        .or(api_v1_create_talk_type)
+        .or(api_v1_update_talk_type)
+        .or(api_v1_delete_talk_type)
+        .or(api_v1_agenda)
+        .or(api_v1_timer)
+        .or(api_v1_suggest)
+        .or(api_v1_merge);
+
+    // Describes the HTTP api and the websocket events
+    let api_docs = warp::path!("api" / "openapi.json")
+        .and(warp::get())
+        .and_then(api::openapi_handler)
+        .or(warp::path!("api" / "schemas" / String / String)
+            .and(warp::get())
+            .and_then(api::event_schema_handler));
+
     // Websocket endpoint
     let ws_route = warp::path("ws")
         .and(warp::ws())
         .and(warp::path::param())
         .and(with_clients(clients.clone()))
+        .and(with_config(config.clone()))
+        .and(with_timer(timer.clone()))
         .and(with_db_access_manager(pool.clone()))
         .and_then(api::ws_handler);
 
@@ -93,6 +464,9 @@ async fn main() {
     let static_files = warp::path("static")
         .and(warp::fs::dir("static"));
 
+    // start the timer broadcasts
+    tokio::spawn(run_timer(timer, clients.clone()));
+
     // start garabage collector
     tokio::spawn(garabage_collector(clients));
 
@@ -111,12 +485,30 @@ async fn main() {
     .and(
         welcome_route
         .or(health_route)
+        .or(present_route)
         .or(all_route)
+        .or(meetings_route)
+        .or(meeting_route)
+        .or(search_route)
+        .or(stats_route)
+        .or(talk_route)
+        .or(history_route)
+        .or(attachment_route)
+        .or(presenters_route)
+        .or(presenter_route)
+        .or(tags_route)
+        .or(tag_route)
+        .or(minutes_route)
+        .or(meeting_minutes_route)
         .or(register)
         .or(authenticate)
         .or(talks)
+        .or(api_talks)
+        .or(api_v1)
+        .or(api_docs)
         .or(ws_route)
         .or(static_files)
+        .recover(api::handle_rejection)
     );
 
     if is_debug {
@@ -136,6 +528,19 @@ fn with_clients(clients: Clients) -> impl Filter<Extract = (Clients,), Error = I
     warp::any().map(move || clients.clone())
 }
 
+// Json request bodies are small, refuse anything larger than 64kb
+fn json_body<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(64 * 1024).and(warp::body::json())
+}
+
+fn with_timer(timer: SharedTimer) -> impl Filter<Extract = (SharedTimer,), Error = Infallible> + Clone {
+    warp::any().map(move || timer.clone())
+}
+
+fn with_config(config: SharedConfig) -> impl Filter<Extract = (SharedConfig,), Error = Infallible> + Clone {
+    warp::any().map(move || config.clone())
+}
+
 type SqlitePool = Pool<ConnectionManager<SqliteConnection>>;
 
 fn sqlite_pool(db_url: &str) -> SqlitePool {
@@ -143,6 +548,22 @@ fn sqlite_pool(db_url: &str) -> SqlitePool {
     Pool::new(manager).expect("Sqlite connection pool could not be created")
 }
 
+// Durations from TALK_DURATIONS replace those stored for the talk types
+fn set_talk_durations(pool: &SqlitePool, durations: &[(String, i32)]) {
+    if durations.is_empty() {
+        return;
+    }
+
+    let db = DBManager::new(pool.get().expect("Sqlite connection could not be opened"));
+    for (name, minutes) in durations {
+        let talk_type = db.find_talk_type(name)
+            .unwrap_or_else(|err| panic!("TALK_DURATIONS could not be applied: {}", err));
+        let changes = UpdateTalkType { duration: Some(*minutes), ..UpdateTalkType::default() };
+        db.update_talk_type(talk_type.id, changes)
+            .unwrap_or_else(|err| panic!("TALK_DURATIONS could not be applied: {}", err));
+    }
+}
+
 fn with_db_access_manager(pool: SqlitePool) -> impl Filter<Extract = (DBManager,), Error = warp::Rejection> + Clone {
     warp::any()
         .map(move || pool.clone())
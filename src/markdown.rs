@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use ammonia::Builder;
+use pulldown_cmark::{Options, Parser, html};
+
+// Tags a description may use, anything else is removed but its text is kept
+const TAGS: &[&str] = &["p", "br", "em", "strong", "del", "code", "pre", "blockquote", "ul", "ol", "li", "a"];
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+// Renders a talk description written in markdown to html that is safe to show on any page
+//
+// Raw html, images and headings are not allowed, links open without a referrer
+// and only point at web pages or email addresses.
+pub fn render(description: &str) -> String {
+    let parser = Parser::new_ext(description, Options::ENABLE_STRIKETHROUGH);
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser);
+
+    Builder::default()
+        .tags(TAGS.iter().copied().collect::<HashSet<_>>())
+        .url_schemes(URL_SCHEMES.iter().copied().collect::<HashSet<_>>())
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn markdown_is_rendered() {
+        assert_eq!(render("Uses **Rust** and `cargo`"), "<p>Uses <strong>Rust</strong> and <code>cargo</code></p>\n");
+    }
+
+    #[test]
+    fn scripts_are_removed() {
+        let html = render("Hi <script>alert(1)</script>");
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(!html.contains("alert"), "{}", html);
+    }
+
+    #[test]
+    fn javascript_links_are_removed() {
+        for description in &["[click](javascript:alert(1))", "<a href=\"javascript:alert(1)\">click</a>"] {
+            let html = render(description);
+            assert!(!html.contains("javascript"), "{}", html);
+            assert!(html.contains("click"), "{}", html);
+        }
+    }
+
+    #[test]
+    fn event_handlers_are_removed() {
+        for description in &["<p onclick=\"alert(1)\">hi</p>", "<img src=x onerror=alert(1)>", "<a href=\"https://example.com\" onmouseover=\"alert(1)\">hi</a>"] {
+            let html = render(description);
+            assert!(!html.contains("onclick") && !html.contains("onerror") && !html.contains("onmouseover"), "{}", html);
+            assert!(!html.contains("<img"), "{}", html);
+        }
+    }
+
+    #[test]
+    fn quotes_cannot_end_an_attribute() {
+        let html = render("[hi](https://example.com \"x\\\" onmouseover=\\\"alert(1)\")");
+        assert!(html.contains("title=\"x&quot; onmouseover=&quot;alert(1)\""), "{}", html);
+    }
+
+    #[test]
+    fn links_open_without_a_referrer() {
+        assert_eq!(render("[slides](https://example.com)"), "<p><a href=\"https://example.com\" rel=\"noopener noreferrer nofollow\">slides</a></p>\n");
+    }
+}
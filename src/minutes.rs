@@ -0,0 +1,87 @@
+use chrono::NaiveDate;
+
+use crate::{model::{Talk, group_by_type}, presenters::join_names};
+
+// Renders talks into the meeting minutes template
+//
+// The template may use `{date}` for the meeting date and `{talks}` for the
+// talks grouped into one markdown section per talk type.
+pub fn render_minutes(template: &str, date: NaiveDate, talks: Vec<Talk>) -> String {
+    let mut sections = String::new();
+
+    for group in group_by_type(talks) {
+        if !sections.is_empty() {
+            sections.push('\n');
+        }
+
+        sections.push_str(&format!("### {}\n\n", group.talk_type.heading));
+        for talk in group.talks {
+            sections.push_str(&format!("* {}", presenters_markdown(&talk)));
+            if !talk.description.trim().is_empty() {
+                sections.push_str(&format!(": {}", description_markdown(&talk.description)));
+            }
+            if !talk.links.is_empty() {
+                sections.push_str(&format!(" ({})", links_markdown(&talk)));
+            }
+            sections.push('\n');
+        }
+    }
+
+    if sections.is_empty() {
+        sections.push_str("No talks.\n");
+    }
+
+    template
+        .replace("{date}", &date.format("%Y-%m-%d").to_string())
+        .replace("{talks}", sections.trim_end())
+}
+
+// Every presenter of a talk in bold, e.g. "**Alice** & **Bob**"
+fn presenters_markdown(talk: &Talk) -> String {
+    let names: Vec<String> = if talk.presenters.is_empty() {
+        vec![format!("**{}**", escape_markdown(&talk.name))]
+    } else {
+        talk.presenters.iter()
+            .map(|presenter| format!("**{}**", escape_markdown(&presenter.name)))
+            .collect()
+    };
+
+    join_names(&names)
+}
+
+// Descriptions are markdown already, later lines are indented to stay in the list item
+fn description_markdown(description: &str) -> String {
+    let lines: Vec<String> = description.trim().lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.trim().is_empty() { line.trim_end().to_string() } else { format!("  {}", line.trim_end()) })
+        .collect();
+
+    lines.join("\n")
+}
+
+// Links of a talk named by kind, e.g. "[slides](https://example.com/talk.pdf)"
+fn links_markdown(talk: &Talk) -> String {
+    let links: Vec<String> = talk.links.iter()
+        .map(|link| format!("[{}]({})", link.kind, link.url.replace('(', "%28").replace(')', "%29")))
+        .collect();
+
+    links.join(", ")
+}
+
+// Escapes characters that markdown would treat as formatting
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.trim().chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
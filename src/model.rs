@@ -1,24 +1,63 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-use crate::schema::talks;
+use crate::markdown;
+use crate::schema::{attachments, comments, links, presenters, tags, talk_presenters, talk_revisions, talk_types, talks, votes};
 
-use diesel::{backend::Backend, serialize::{ToSql, Output}, sql_types::Integer};
+use diesel::{backend::Backend, serialize::{ToSql, Output}, sql_types::{BigInt, Date, Integer, Text}};
 use std::{fmt::Display, io::Write};
 use diesel::{serialize, deserialize};
 use diesel::deserialize::FromSql;
+use chrono::{NaiveDate, NaiveDateTime};
 
-#[derive(Deserialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+// A kind of talk, admins manage them in the talk_types table
+//
+// `slug` names the type in requests, e.g. "LightningTalk", and `label` is
+// shown next to talks, e.g. "lightning talk". Talks are presented by
+// `position`, lowest first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, JsonSchema)]
+pub struct TalkType {
+    pub id: i32,
+    pub slug: String,
+    pub label: String,
+    // Section heading when talks are grouped by type, e.g. "Lightning Talks"
+    pub heading: String,
+    pub position: i32,
+    // Css color, e.g. "#ffca28"
+    pub color: String,
+    // Minutes a talk of this type is expected to take
+    pub duration: i32,
+    // Whether talks of this type are voted on to decide what to discuss first
+    pub accepts_votes: bool,
+    // Whether talks of this type take place once the meeting is over
+    pub after_meeting: bool,
+}
+
+impl TalkType {
+    // Whether `name` is the slug or the label of this type
+    pub fn is_named(&self, name: &str) -> bool {
+        self.slug == name || self.label.eq_ignore_ascii_case(name)
+    }
+}
+
+impl Display for TalkType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+// What a link of a talk points at
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 #[sql_type = "Integer"]
-pub enum TalkType {
-    ForumTopic,
-    LightningTalk,
-    ProjectUpdate,
-	Announcement,
-    AfterMeetingSlot
+pub enum LinkKind {
+    Slides,
+    Repo,
+    Video,
+    Other,
 }
 
-// Converts TalkType enum to sql interger
-impl<DB> ToSql<Integer, DB> for TalkType
+impl<DB> ToSql<Integer, DB> for LinkKind
 where
     DB: Backend,
     i32: ToSql<Integer, DB>,
@@ -28,55 +67,478 @@ where
     }
 }
 
-impl<DB> FromSql<Integer, DB> for TalkType
+impl<DB> FromSql<Integer, DB> for LinkKind
 where
     DB: Backend,
     i32: FromSql<Integer, DB>,
 {
     fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
         match i32::from_sql(bytes)? {
-            0 => Ok(TalkType::ForumTopic),
-            1 => Ok(TalkType::LightningTalk),
-            2 => Ok(TalkType::ProjectUpdate),
-            3 => Ok(TalkType::Announcement),
-            4 => Ok(TalkType::AfterMeetingSlot),
-            int => Err(format!("Invalid TalkType {}", int).into()),
+            0 => Ok(LinkKind::Slides),
+            1 => Ok(LinkKind::Repo),
+            2 => Ok(LinkKind::Video),
+            3 => Ok(LinkKind::Other),
+            int => Err(format!("Invalid LinkKind {}", int).into()),
         }
     }
 }
 
-impl Serialize for TalkType {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: serde::Serializer {
-        serializer.serialize_str(match *self {
-            TalkType::ForumTopic => "forum topic",
-            TalkType::LightningTalk => "lightning talk",
-            TalkType::ProjectUpdate => "project update",
-            TalkType::Announcement => "announcement",
-            TalkType::AfterMeetingSlot => "after meeting slot"
-        })
+impl Display for LinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            LinkKind::Slides => { f.write_str("slides") }
+            LinkKind::Repo => { f.write_str("repo") }
+            LinkKind::Video => { f.write_str("video") }
+            LinkKind::Other => { f.write_str("link") }
+        }
     }
 }
 
-impl Display for TalkType {
+// Where a talk is in its lifecycle, see TalkStatus::can_become
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Integer"]
+pub enum TalkStatus {
+    // Submitted and waiting for a moderator
+    Proposed,
+    // Up for the next meeting
+    Scheduled,
+    Presenting,
+    Presented,
+    // Taken back by its presenter
+    Withdrawn,
+    // Turned down by a moderator
+    Rejected,
+}
+
+impl TalkStatus {
+    // Whether a talk may move from this status to `next`
+    //
+    // Going back to the previous talk during the meeting undoes the last step,
+    // and withdrawn talks may be scheduled again.
+    pub fn can_become(self, next: TalkStatus) -> bool {
+        use TalkStatus::*;
+
+        matches!((self, next),
+            (Proposed, Scheduled) | (Proposed, Withdrawn) | (Proposed, Rejected)
+            | (Scheduled, Presenting) | (Scheduled, Presented) | (Scheduled, Withdrawn) | (Scheduled, Rejected)
+            | (Presenting, Presented) | (Presenting, Scheduled)
+            | (Presented, Presenting)
+            | (Withdrawn, Scheduled))
+    }
+
+    // Proposed and rejected talks are only shown to moderators
+    pub fn is_listed(self) -> bool {
+        !matches!(self, TalkStatus::Proposed | TalkStatus::Rejected)
+    }
+}
+
+impl<DB> ToSql<Integer, DB> for TalkStatus
+where
+    DB: Backend,
+    i32: ToSql<Integer, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        (*self as i32).to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Integer, DB> for TalkStatus
+where
+    DB: Backend,
+    i32: FromSql<Integer, DB>,
+{
+    fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+        match i32::from_sql(bytes)? {
+            0 => Ok(TalkStatus::Proposed),
+            1 => Ok(TalkStatus::Scheduled),
+            2 => Ok(TalkStatus::Presenting),
+            3 => Ok(TalkStatus::Presented),
+            4 => Ok(TalkStatus::Withdrawn),
+            5 => Ok(TalkStatus::Rejected),
+            int => Err(format!("Invalid TalkStatus {}", int).into()),
+        }
+    }
+}
+
+impl Display for TalkStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
-            TalkType::ForumTopic => { f.write_str("forum topic") }
-            TalkType::LightningTalk => { f.write_str("lightning talk") }
-            TalkType::ProjectUpdate => { f.write_str("project update") }
-            TalkType::Announcement => { f.write_str("announcement") }
-            TalkType::AfterMeetingSlot => { f.write_str("after meeting slot") }
+            TalkStatus::Proposed => { f.write_str("proposed") }
+            TalkStatus::Scheduled => { f.write_str("scheduled") }
+            TalkStatus::Presenting => { f.write_str("presenting") }
+            TalkStatus::Presented => { f.write_str("presented") }
+            TalkStatus::Withdrawn => { f.write_str("withdrawn") }
+            TalkStatus::Rejected => { f.write_str("rejected") }
         }
     }
 }
 
-#[derive(Serialize, Debug, Clone, Queryable)]
+// A talk as stored in the talks table, see Talk
+#[derive(Debug, Clone, Queryable, QueryableByName)]
+#[table_name = "talks"]
+pub struct TalkRow {
+    pub id: i32,
+    pub name: String,
+    // Id of the talk type
+    pub talk_type: i32,
+    pub description: String,
+    pub meeting: Option<NaiveDate>,
+    pub duration: Option<i32>,
+    pub started_at: Option<NaiveDateTime>,
+    pub presented_at: Option<NaiveDateTime>,
+    pub votes: i32,
+    pub status: TalkStatus,
+    // Client id of the visitor who proposed the talk
+    pub submitted_by: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub hidden_at: Option<NaiveDateTime>,
+}
+
+impl TalkRow {
+    pub fn with_details(self, talk_type: TalkType, presenters: Vec<Presenter>, links: Vec<Link>, tags: Vec<String>) -> Talk {
+        Talk {
+            id: self.id,
+            name: self.name,
+            type_label: talk_type.label.clone(),
+            talk_type,
+            description_html: markdown::render(&self.description),
+            description: self.description,
+            status: self.status,
+            meeting: self.meeting,
+            duration: self.duration,
+            started_at: self.started_at,
+            presented_at: self.presented_at,
+            votes: self.votes,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            hidden_at: self.hidden_at,
+            presenters,
+            links,
+            tags,
+        }
+    }
+}
+
+// A talk and the people presenting it
+//
+// `name` lists every presenter, e.g. "Alice & Bob", and is kept for clients
+// that show a single name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct Talk {
     pub id: i32,
     pub name: String,
+    // Sent as `talk_type`, the label clients got before talk types were stored in a table
+    #[serde(rename = "talk_type")]
+    pub type_label: String,
+    // Sent as `type`, the whole talk type
+    #[serde(rename = "type")]
     pub talk_type: TalkType,
+    // Markdown, and the sanitized html rendered from it
     pub description: String,
-    pub is_visible: bool
+    pub description_html: String,
+    pub status: TalkStatus,
+    // The meeting the talk was archived at, None while it is upcoming
+    pub meeting: Option<NaiveDate>,
+    // Expected minutes when they differ from the default for the talk type
+    pub duration: Option<i32>,
+    // When the talk started and ended during the meeting
+    pub started_at: Option<NaiveDateTime>,
+    pub presented_at: Option<NaiveDateTime>,
+    // Forum topics are voted on to decide what to discuss first
+    pub votes: i32,
+    // When the talk was added and last changed, and archived by hiding it
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub hidden_at: Option<NaiveDateTime>,
+    // In the order they were given
+    pub presenters: Vec<Presenter>,
+    pub links: Vec<Link>,
+    // Alphabetical, e.g. ["hardware", "rust"]
+    pub tags: Vec<String>,
+}
+
+impl Talk {
+    // Whether this talk is being presented right now
+    pub fn is_current(&self) -> bool {
+        self.status == TalkStatus::Presenting
+    }
+
+    pub fn accepts_votes(&self) -> bool {
+        self.talk_type.accepts_votes
+    }
+
+    // Minutes the talk is expected to take
+    pub fn minutes(&self) -> i32 {
+        self.duration.unwrap_or(self.talk_type.duration)
+    }
+}
+
+// Slides, a repository or a video of a talk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, JsonSchema)]
+pub struct Link {
+    pub kind: LinkKind,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "links"]
+pub struct TalkLink<'a> {
+    pub talk_id: i32,
+    pub position: i32,
+    pub kind: LinkKind,
+    pub url: &'a str,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "tags"]
+pub struct TalkTag<'a> {
+    pub talk_id: i32,
+    pub tag: &'a str,
+}
+
+// A tag and how many talks have it
+#[derive(Serialize, Debug, Clone, QueryableByName, JsonSchema)]
+pub struct TagSummary {
+    #[sql_type = "Text"]
+    pub tag: String,
+    #[sql_type = "BigInt"]
+    pub talks: i64,
+}
+
+// Every talk with a tag, newest first
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct TaggedTalks {
+    pub tag: String,
+    pub talks: Vec<Talk>,
+}
+
+// A question or follow-up left on a talk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, JsonSchema)]
+pub struct Comment {
+    pub id: i32,
+    pub talk_id: i32,
+    pub author: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    // Admins hide comments that should not be shown
+    pub is_visible: bool,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "comments"]
+pub struct CreateComment<'a> {
+    pub talk_id: i32,
+    pub author: &'a str,
+    pub body: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+// An earlier version of a talk, replaced at `replaced_at` by an edit or a revert
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct TalkRevision {
+    pub id: i32,
+    pub talk_id: i32,
+    pub name: String,
+    // Id of the talk type, which may have been deleted since
+    pub talk_type: i32,
+    pub description: String,
+    pub duration: Option<i32>,
+    // Presenter names in the order they were given
+    pub presenters: Vec<String>,
+    pub links: Vec<Link>,
+    pub tags: Vec<String>,
+    pub replaced_at: NaiveDateTime,
+}
+
+// A talk revision as stored, with its lists as json
+#[derive(Debug, Clone, Queryable)]
+pub struct RevisionRow {
+    pub id: i32,
+    pub talk_id: i32,
+    pub name: String,
+    pub talk_type: i32,
+    pub description: String,
+    pub duration: Option<i32>,
+    pub presenters: String,
+    pub links: String,
+    pub tags: String,
+    pub replaced_at: NaiveDateTime,
+}
+
+impl RevisionRow {
+    pub fn parse(self) -> serde_json::Result<TalkRevision> {
+        Ok(TalkRevision {
+            id: self.id,
+            talk_id: self.talk_id,
+            name: self.name,
+            talk_type: self.talk_type,
+            description: self.description,
+            duration: self.duration,
+            presenters: serde_json::from_str(&self.presenters)?,
+            links: serde_json::from_str(&self.links)?,
+            tags: serde_json::from_str(&self.tags)?,
+            replaced_at: self.replaced_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "talk_revisions"]
+pub struct CreateRevision<'a> {
+    pub talk_id: i32,
+    pub name: &'a str,
+    pub talk_type: i32,
+    pub description: &'a str,
+    pub duration: Option<i32>,
+    pub presenters: String,
+    pub links: String,
+    pub tags: String,
+    pub replaced_at: NaiveDateTime,
+}
+
+// A slide file uploaded for a talk, the file is stored on disk under its hash
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, JsonSchema)]
+pub struct Attachment {
+    pub id: i32,
+    pub talk_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    // Size in bytes
+    pub size: i64,
+    pub sha256: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "attachments"]
+pub struct CreateAttachment<'a> {
+    pub talk_id: i32,
+    pub file_name: &'a str,
+    pub content_type: &'a str,
+    pub size: i64,
+    pub sha256: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+// A talk with its attachments and the comments left on it, oldest first
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct TalkDetail {
+    pub talk: Talk,
+    pub attachments: Vec<Attachment>,
+    pub comments: Vec<Comment>,
+}
+
+// A talk that was presented during the current meeting
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct Presented {
+    pub id: i32,
+    pub presented_at: NaiveDateTime,
+}
+
+// A person giving talks, talks keep a copy of the name for display
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, QueryableByName, JsonSchema)]
+#[table_name = "presenters"]
+pub struct Presenter {
+    pub id: i32,
+    pub name: String,
+}
+
+// A presenter and how many talks they have given
+#[derive(Serialize, Debug, Clone, QueryableByName, JsonSchema)]
+pub struct PresenterSummary {
+    #[sql_type = "Integer"]
+    pub id: i32,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "BigInt"]
+    pub talks: i64,
+}
+
+// Every talk of a presenter, newest first
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct PresenterHistory {
+    pub presenter: Presenter,
+    pub talks: Vec<Talk>,
+}
+
+// A past meeting and how many talks were archived at it
+#[derive(Serialize, Debug, Clone, QueryableByName, JsonSchema)]
+pub struct Meeting {
+    #[sql_type = "Date"]
+    pub date: NaiveDate,
+    #[sql_type = "BigInt"]
+    pub talks: i64,
+}
+
+// A talk matching a full-text search
+//
+// `name_html` and `snippet_html` are escaped with the matching terms wrapped in <mark>
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct SearchResult {
+    pub talk: Talk,
+    pub name_html: String,
+    pub snippet_html: String,
+    pub rank: f64,
+}
+
+// Orders a listing of talks can be sorted in
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TalkSort {
+    #[default]
+    Oldest,
+    Newest,
+    Type,
+}
+
+// Filters, ordering and paging for a listing of talks
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TalkQuery {
+    pub status: Option<TalkStatus>,
+    // From before talks had a status, upcoming talks or archived ones, see DBManager::list_talks
+    pub visible: Option<bool>,
+    // Slug or label of a talk type
+    #[serde(rename = "type")]
+    pub talk_type: Option<String>,
+    pub meeting: Option<NaiveDate>,
+    pub presenter: Option<String>,
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub sort: TalkSort,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// One page of a listing of talks
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct TalkPage {
+    pub talks: Vec<Talk>,
+    // Pass back as `cursor` to get the following page
+    pub next_cursor: Option<String>,
+}
+
+// A group of talks sharing the same type
+pub struct TalkGroup {
+    pub talk_type: TalkType,
+    pub talks: Vec<Talk>,
+}
+
+// Groups talks by type, in the order the types first appear in `talks`
+//
+// Talks listed in meeting order give the groups in meeting order.
+pub fn group_by_type(talks: Vec<Talk>) -> Vec<TalkGroup> {
+    let mut groups: Vec<TalkGroup> = Vec::new();
+
+    for talk in talks {
+        match groups.iter_mut().find(|group| group.talk_type.id == talk.talk_type.id) {
+            Some(group) => { group.talks.push(talk); }
+            None => { groups.push(TalkGroup { talk_type: talk.talk_type.clone(), talks: vec![talk] }); }
+        }
+    }
+
+    groups
 }
 
 // Struct for creating Book
@@ -84,7 +546,109 @@ pub struct Talk {
 #[table_name = "talks"]
 pub struct CreateTalk<'a> {
     pub name: &'a String,
-    pub talk_type: TalkType,
+    pub talk_type: i32,
     pub description: &'a String,
-    pub is_visible: bool
+    pub status: TalkStatus,
+    pub duration: Option<i32>,
+    pub submitted_by: Option<&'a str>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// Struct for editing a talk, fields left as None are unchanged
+#[derive(Debug, Clone, AsChangeset)]
+#[table_name = "talks"]
+pub struct UpdateTalk<'a> {
+    pub name: Option<&'a String>,
+    pub talk_type: Option<i32>,
+    pub description: Option<&'a String>,
+    pub duration: Option<i32>,
+}
+
+impl UpdateTalk<'_> {
+    // Whether no column changes, e.g. when only the links of a talk are replaced
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.talk_type.is_none() && self.description.is_none() && self.duration.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "talk_types"]
+pub struct CreateTalkType<'a> {
+    pub slug: &'a str,
+    pub label: &'a str,
+    pub heading: &'a str,
+    pub position: i32,
+    pub color: &'a str,
+    pub duration: i32,
+    pub accepts_votes: bool,
+    pub after_meeting: bool,
+}
+
+// Struct for editing a talk type, fields left as None are unchanged
+#[derive(Debug, Clone, Default, AsChangeset)]
+#[table_name = "talk_types"]
+pub struct UpdateTalkType<'a> {
+    pub slug: Option<&'a str>,
+    pub label: Option<&'a str>,
+    pub heading: Option<&'a str>,
+    pub position: Option<i32>,
+    pub color: Option<&'a str>,
+    pub duration: Option<i32>,
+    pub accepts_votes: Option<bool>,
+    pub after_meeting: Option<bool>,
+}
+
+// Links a talk to one of its presenters
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "talk_presenters"]
+pub struct TalkPresenter {
+    pub talk_id: i32,
+    pub presenter_id: i32,
+    pub position: i32,
+}
+
+// A client's vote for a talk
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "votes"]
+pub struct Vote<'a> {
+    pub talk_id: i32,
+    pub client_id: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TalkStatus::{self, *};
+
+    const ALL: [TalkStatus; 6] = [Proposed, Scheduled, Presenting, Presented, Withdrawn, Rejected];
+    const ALLOWED: [(TalkStatus, TalkStatus); 11] = [
+        (Proposed, Scheduled), (Proposed, Withdrawn), (Proposed, Rejected),
+        (Scheduled, Presenting), (Scheduled, Presented), (Scheduled, Withdrawn), (Scheduled, Rejected),
+        (Presenting, Presented), (Presenting, Scheduled),
+        (Presented, Presenting),
+        (Withdrawn, Scheduled),
+    ];
+
+    #[test]
+    fn allowed_transitions() {
+        for &(from, to) in &ALLOWED {
+            assert!(from.can_become(to), "{:?} should become {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn every_other_transition_is_refused() {
+        for &from in &ALL {
+            for &to in &ALL {
+                if !ALLOWED.contains(&(from, to)) {
+                    assert!(!from.can_become(to), "{:?} should not become {:?}", from, to);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_talks_are_final() {
+        assert!(ALL.iter().all(|&to| !Rejected.can_become(to)));
+    }
 }
@@ -0,0 +1,542 @@
+use schemars::{JsonSchema, gen::{SchemaGenerator, SchemaSettings}, schema::Schema};
+use serde_json::{Value, json};
+
+use crate::{agenda::Agenda, api::{AuthenticateRequest, AuthenticateResponse, CreateCommentRequest, CreateTalkRequest, CreateTalkTypeRequest, ErrorResponse, MergePresentersRequest, RegisterResponse, RejectRequest, SetStatusRequest, UpdateTalkRequest, UpdateTalkTypeRequest}, events::{EventRequest, EventResponse}, model::{Attachment, Comment, PresenterHistory, PresenterSummary, SearchResult, TagSummary, TaggedTalks, Talk, TalkDetail, TalkPage, TalkStatus, TalkType}, revisions::RevisionChanges, stats::Stats, timer::TimerState};
+
+const JSON_SCHEMA_DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+// Builds the OpenAPI document describing the HTTP api
+pub fn openapi() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+
+    let talk = gen.subschema_for::<Talk>();
+    let talk_page = gen.subschema_for::<TalkPage>();
+    let talk_type = gen.subschema_for::<TalkType>();
+    let talk_types = gen.subschema_for::<Vec<TalkType>>();
+    let create_talk_type = gen.subschema_for::<CreateTalkTypeRequest>();
+    let update_talk_type = gen.subschema_for::<UpdateTalkTypeRequest>();
+    let search_results = gen.subschema_for::<Vec<SearchResult>>();
+    let create_talk = gen.subschema_for::<CreateTalkRequest>();
+    let update_talk = gen.subschema_for::<UpdateTalkRequest>();
+    let talk_status = gen.subschema_for::<TalkStatus>();
+    let set_status = gen.subschema_for::<SetStatusRequest>();
+    let reject = gen.subschema_for::<RejectRequest>();
+    let register = gen.subschema_for::<RegisterResponse>();
+    let authenticate = gen.subschema_for::<AuthenticateRequest>();
+    let authenticated = gen.subschema_for::<AuthenticateResponse>();
+    let talks = gen.subschema_for::<Vec<Talk>>();
+    let stats = gen.subschema_for::<Stats>();
+    let agenda = gen.subschema_for::<Agenda>();
+    let timer = gen.subschema_for::<TimerState>();
+    let talk_detail = gen.subschema_for::<TalkDetail>();
+    let history = gen.subschema_for::<Vec<RevisionChanges>>();
+    let attachment = gen.subschema_for::<Attachment>();
+    let attachments = gen.subschema_for::<Vec<Attachment>>();
+    let comment = gen.subschema_for::<Comment>();
+    let comments = gen.subschema_for::<Vec<Comment>>();
+    let create_comment = gen.subschema_for::<CreateCommentRequest>();
+    let presenters = gen.subschema_for::<Vec<PresenterSummary>>();
+    let presenter_history = gen.subschema_for::<PresenterHistory>();
+    let merge_presenters = gen.subschema_for::<MergePresentersRequest>();
+    let tags = gen.subschema_for::<Vec<TagSummary>>();
+    let tagged_talks = gen.subschema_for::<TaggedTalks>();
+    gen.subschema_for::<EventRequest>();
+    gen.subschema_for::<EventResponse>();
+    gen.subschema_for::<ErrorResponse>();
+
+    let string = json!({ "type": "string" });
+    let date = json!({ "type": "string", "format": "date" });
+    let talk_id = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } });
+    let talk_type_id = talk_id.clone();
+    let client_id = json!({
+        "name": "x-client-id",
+        "in": "header",
+        "required": true,
+        "description": "Id returned by /register, the client must be authenticated",
+        "schema": string,
+    });
+    let client_id_visitor = json!({
+        "name": "x-client-id",
+        "in": "header",
+        "required": false,
+        "description": "Id returned by /register, talks of clients that are not authenticated wait for a moderator",
+        "schema": string,
+    });
+    let client_id_moderator = json!({
+        "name": "x-client-id",
+        "in": "header",
+        "required": false,
+        "description": "Id returned by /register, only admins are answered about proposed and rejected talks",
+        "schema": string,
+    });
+    let client_id_admin = json!({
+        "name": "x-client-id",
+        "in": "header",
+        "required": true,
+        "description": "Id returned by /register, the client must be authenticated with the admin password",
+        "schema": string,
+    });
+    // Slide files are uploaded as the file field of a multipart form
+    let upload = json!({
+        "required": true,
+        "content": { "multipart/form-data": { "schema": { "type": "object", "properties": { "file": { "type": "string", "format": "binary" } }, "required": ["file"] } } },
+    });
+    let listing = json!({
+        "summary": "Lists talks with filters and paging",
+        "parameters": [
+            query("status", json!(talk_status), "Only talks with this status, withdrawn talks are left out otherwise"),
+            query("visible", json!({ "type": "boolean" }), "Deprecated, true lists scheduled and presenting talks and false presented talks"),
+            query("type", string.clone(), "Only talks of this type, by slug or label"),
+            query("meeting", date.clone(), "Only talks archived at this meeting"),
+            query("presenter", string.clone(), "Part of the presenter's name"),
+            query("tag", string.clone(), "Only talks with this tag"),
+            query("sort", json!({ "type": "string", "enum": ["oldest", "newest", "type"] }), "Order of the talks, oldest first by default"),
+            query("cursor", string.clone(), "next_cursor of the previous page"),
+            query("limit", json!({ "type": "integer", "minimum": 1, "maximum": 200 }), "Page size, 50 by default"),
+        ],
+        "responses": { "200": ok("A page of talks", &talk_page), "400": error("Invalid query") },
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Talks",
+            "description": "Manages talks at COSI meetings. Live updates are sent over the websocket as EventResponse messages, and authenticated clients send EventRequest messages.",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/health": {
+                "get": { "summary": "Indicates whether the service is up", "responses": { "200": text("Number of open connections", "text/plain") } },
+            },
+            "/register": {
+                "post": { "summary": "Registers a new client for live updates", "responses": { "200": ok("The client id", &register) } },
+            },
+            "/authenticate": {
+                "post": {
+                    "summary": "Authenticates a client",
+                    "requestBody": body(&authenticate),
+                    "responses": {
+                        "200": ok("Authenticated, as an admin with the admin password", &authenticated),
+                        "400": error("Unknown client"),
+                        "403": error("Wrong password"),
+                    },
+                },
+            },
+            "/ws/{id}": {
+                "get": {
+                    "summary": "Websocket endpoint",
+                    "description": "Clients send EventRequest messages and receive EventResponse messages, see components",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": string }],
+                    "responses": { "101": { "description": "Switching to the websocket protocol" }, "404": { "description": "Unknown client" } },
+                },
+            },
+            "/talks": {
+                "get": { "summary": "Returns the currently visible talks", "responses": { "200": ok("Visible talks", &talks) } },
+            },
+            "/api/talks": { "get": listing },
+            "/api/v1/talks": {
+                "get": listing,
+                "post": {
+                    "summary": "Creates a talk",
+                    "description": "Talks of authenticated clients are scheduled and broadcast as a Show event, the others are proposed and sent to admins as a Proposed event",
+                    "parameters": [client_id_visitor],
+                    "requestBody": body(&create_talk),
+                    "responses": { "201": ok("The new talk", &talk), "400": error("Invalid talk") },
+                },
+            },
+            "/api/v1/talks/{id}": {
+                "get": {
+                    "summary": "Returns a talk",
+                    "parameters": [talk_id],
+                    "responses": { "200": ok("The talk", &talk), "404": error("No such talk") },
+                },
+                "patch": {
+                    "summary": "Edits a talk",
+                    "parameters": [talk_id, client_id],
+                    "requestBody": body(&update_talk),
+                    "responses": { "200": ok("The edited talk", &talk), "400": error("Invalid changes"), "403": error("Client is not authenticated"), "404": error("No such talk") },
+                },
+                "delete": {
+                    "summary": "Hides a talk",
+                    "parameters": [talk_id, client_id],
+                    "responses": { "204": { "description": "Hidden" }, "403": error("Client is not authenticated"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/status": {
+                "put": {
+                    "summary": "Moves a talk to another status",
+                    "description": "Authenticated clients may withdraw talks, admins also schedule and reject them. The change is broadcast as a Status event",
+                    "parameters": [talk_id.clone(), client_id.clone()],
+                    "requestBody": body(&set_status),
+                    "responses": { "200": ok("The talk", &talk), "400": error("The talk cannot move to this status"), "403": error("Client may not make this change"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/proposals": {
+                "get": {
+                    "summary": "Talks proposed by visitors that wait for a moderator, oldest first",
+                    "parameters": [client_id_admin.clone()],
+                    "responses": { "200": ok("Proposed talks", &talks), "403": error("Client is not an admin") },
+                },
+            },
+            "/api/v1/talks/{id}/approve": {
+                "post": {
+                    "summary": "Approves a proposed talk",
+                    "description": "The talk is scheduled and broadcast as a Show event",
+                    "parameters": [talk_id.clone(), client_id_admin.clone()],
+                    "responses": { "200": ok("The scheduled talk", &talk), "400": error("The talk is not proposed"), "403": error("Client is not an admin"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/reject": {
+                "post": {
+                    "summary": "Rejects a proposed talk",
+                    "description": "The reason is sent to the visitor who proposed the talk as a Rejected event",
+                    "parameters": [talk_id.clone(), client_id_admin.clone()],
+                    "requestBody": body(&reject),
+                    "responses": { "200": ok("The rejected talk", &talk), "400": error("The talk is not proposed"), "403": error("Client is not an admin"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/votes": {
+                "post": {
+                    "summary": "Votes for a forum topic",
+                    "description": "Every client may vote for a forum topic once, the new count is broadcast as a Vote event",
+                    "parameters": [talk_id, client_id],
+                    "responses": { "200": ok("The talk with its votes", &talk), "400": error("Not a forum topic or already voted"), "403": error("Client is not authenticated"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/revisions": {
+                "get": {
+                    "summary": "Every earlier version of a talk, newest first",
+                    "description": "Each revision lists the fields the next version changed, the newest one is compared with the talk as it is now",
+                    "parameters": [talk_id],
+                    "responses": { "200": ok("Revisions with their changes", &history), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/revisions/{revision}/revert": {
+                "post": {
+                    "summary": "Puts a talk back the way it was at a revision",
+                    "description": "The version being replaced is kept as a revision too, and the talk is broadcast as an Update event",
+                    "parameters": [talk_id, { "name": "revision", "in": "path", "required": true, "schema": { "type": "integer" } }, client_id_admin.clone()],
+                    "responses": { "200": ok("The reverted talk", &talk), "400": error("The talk type of the revision was deleted"), "403": error("Client is not an admin"), "404": error("No such talk or revision") },
+                },
+            },
+            "/api/v1/talks/{id}/comments": {
+                "get": {
+                    "summary": "Visible comments on a talk, oldest first",
+                    "parameters": [talk_id, client_id_moderator.clone()],
+                    "responses": { "200": ok("Comments", &comments), "404": error("No such talk") },
+                },
+                "post": {
+                    "summary": "Comments on a talk",
+                    "description": "The comment is sent as a Comment event to the clients watching the talk",
+                    "parameters": [talk_id, client_id],
+                    "requestBody": body(&create_comment),
+                    "responses": { "201": ok("The new comment", &comment), "400": error("Empty or too long comment"), "403": error("Client is not authenticated"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/talks/{id}/attachments": {
+                "get": {
+                    "summary": "Slide files uploaded for a talk, oldest first",
+                    "parameters": [talk_id, client_id_moderator.clone()],
+                    "responses": { "200": ok("Attachments", &attachments), "404": error("No such talk") },
+                },
+                "post": {
+                    "summary": "Uploads a slide file for a talk",
+                    "description": "The file is sent as the file field of a multipart form and must be a pdf, odp, pptx or ppt. New attachments are sent as an Attachment event to the clients watching the talk",
+                    "parameters": [talk_id, client_id],
+                    "requestBody": upload,
+                    "responses": { "200": ok("The talk already has this file", &attachment), "201": ok("The new attachment", &attachment), "400": error("Not a slide file, too large or over the talk's quota"), "403": error("Client is not authenticated"), "404": error("No such talk") },
+                },
+            },
+            "/api/v1/comments/{id}": {
+                "delete": {
+                    "summary": "Hides a comment",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }, client_id_admin],
+                    "responses": { "204": { "description": "Hidden" }, "403": error("Client is not an admin"), "404": error("No such comment") },
+                },
+            },
+            "/api/v1/agenda": {
+                "get": {
+                    "summary": "The visible talks laid out over today's meeting",
+                    "description": "Projects when every talk starts from the expected duration of each talk, overrun is more than 0 when the talks do not fit in the meeting",
+                    "responses": { "200": ok("Timed agenda", &agenda) },
+                },
+            },
+            "/api/v1/timer": {
+                "get": {
+                    "summary": "Time left for the talk being presented",
+                    "description": "Admins start, pause and extend the timer over the websocket, Timer events carry the same fields every second while it runs",
+                    "responses": { "200": ok("Timer", &timer) },
+                },
+            },
+            "/api/v1/presenters/suggest": {
+                "get": {
+                    "summary": "Existing presenters with names similar to a new one",
+                    "parameters": [query("name", string.clone(), "Name being typed")],
+                    "responses": { "200": ok("Similar presenters, closest first", &presenters) },
+                },
+            },
+            "/api/v1/presenters/merge": {
+                "post": {
+                    "summary": "Moves every talk of one presenter to another and removes the first",
+                    "parameters": [client_id_admin],
+                    "requestBody": body(&merge_presenters),
+                    "responses": { "200": ok("The remaining presenter", &presenter_history), "400": error("Cannot merge a presenter into itself"), "403": error("Client is not an admin"), "404": error("No such presenter") },
+                },
+            },
+            "/api/v1/talk-types": {
+                "get": {
+                    "summary": "Every talk type in the order talks are presented",
+                    "responses": { "200": ok("Talk types", &talk_types) },
+                },
+                "post": {
+                    "summary": "Adds a talk type",
+                    "description": "Every change to the talk types is sent to all clients as a TalkTypes event",
+                    "parameters": [client_id_admin.clone()],
+                    "requestBody": body(&create_talk_type),
+                    "responses": { "201": ok("The new talk type", &talk_type), "400": error("Invalid or duplicate slug, label or color"), "403": error("Client is not an admin") },
+                },
+            },
+            "/api/v1/talk-types/{id}": {
+                "patch": {
+                    "summary": "Edits a talk type",
+                    "parameters": [talk_type_id.clone(), client_id_admin.clone()],
+                    "requestBody": body(&update_talk_type),
+                    "responses": { "200": ok("The talk type", &talk_type), "400": error("Invalid or duplicate slug, label or color"), "403": error("Client is not an admin"), "404": error("No such talk type") },
+                },
+                "delete": {
+                    "summary": "Deletes a talk type that no talk has",
+                    "parameters": [talk_type_id, client_id_admin.clone()],
+                    "responses": { "204": { "description": "Deleted" }, "400": error("Talks still have this type"), "403": error("Client is not an admin"), "404": error("No such talk type") },
+                },
+            },
+            "/presenters": {
+                "get": {
+                    "summary": "Lists everyone who has given a talk",
+                    "description": "Browsers asking for text/html get the presenters page",
+                    "responses": { "200": ok("Presenters by name", &presenters) },
+                },
+            },
+            "/talks/{id}": {
+                "get": {
+                    "summary": "A talk with its attachments and comments",
+                    "description": "Browsers asking for text/html get the talk's page, which watches the talk for new comments and attachments",
+                    "parameters": [talk_id],
+                    "responses": { "200": ok("The talk, its attachments and its visible comments", &talk_detail), "404": error("No such talk") },
+                },
+            },
+            "/talks/{id}/history": {
+                "get": {
+                    "summary": "Every earlier version of a talk, newest first",
+                    "description": "Browsers asking for text/html get the talk's history page",
+                    "parameters": [talk_id],
+                    "responses": { "200": ok("Revisions with their changes", &history), "404": error("No such talk") },
+                },
+            },
+            "/attachments/{id}": {
+                "get": {
+                    "summary": "Downloads an attachment under the name it was uploaded with",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }],
+                    "responses": { "200": text("The file", "application/octet-stream"), "404": error("No such attachment") },
+                },
+            },
+            "/presenters/{id}": {
+                "get": {
+                    "summary": "Every talk given by a presenter",
+                    "description": "Browsers asking for text/html get the presenter's page",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }],
+                    "responses": { "200": ok("The presenter and their talks, newest first", &presenter_history), "404": error("No such presenter") },
+                },
+            },
+            "/tags": {
+                "get": {
+                    "summary": "Lists every tag in use",
+                    "description": "Browsers asking for text/html get the tag cloud",
+                    "responses": { "200": ok("Tags and how many talks have them, alphabetically", &tags) },
+                },
+            },
+            "/tags/{tag}": {
+                "get": {
+                    "summary": "Every talk with a tag, newest first",
+                    "description": "Browsers asking for text/html get the tag's page",
+                    "parameters": [{ "name": "tag", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": { "200": ok("The tag and its talks", &tagged_talks), "404": error("No talk has this tag") },
+                },
+            },
+            "/search": {
+                "get": {
+                    "summary": "Full-text search over every talk",
+                    "parameters": [
+                        query("q", string.clone(), "Words to search for"),
+                        query("type", string.clone(), "Only talks of this type, by slug or label"),
+                        query("tag", string.clone(), "Only talks with this tag"),
+                        query("from", date.clone(), "Only talks presented on or after"),
+                        query("to", date.clone(), "Only talks presented on or before"),
+                    ],
+                    "responses": { "200": ok("Matching talks, best first", &search_results) },
+                },
+            },
+            "/stats": {
+                "get": {
+                    "summary": "Statistics over every talk",
+                    "description": "Browsers asking for text/html get the statistics page with charts",
+                    "responses": { "200": ok("Talk counts by type, presenter and semester", &stats) },
+                },
+            },
+            "/api/openapi.json": {
+                "get": { "summary": "This document", "responses": { "200": text("OpenAPI document", "application/json") } },
+            },
+            "/api/schemas/{direction}/{event}.json": {
+                "get": {
+                    "summary": "Json schema of a websocket event",
+                    "parameters": [
+                        { "name": "direction", "in": "path", "required": true, "schema": { "type": "string", "enum": ["request", "response"] } },
+                        { "name": "event", "in": "path", "required": true, "schema": string },
+                    ],
+                    "responses": { "200": text("Json schema", "application/json"), "404": error("No such event") },
+                },
+            },
+            "/present": {
+                "get": { "summary": "Read-only projector view of the running order", "responses": { "200": text("Projector page", "text/html") } },
+            },
+            "/meetings": {
+                "get": { "summary": "Lists past meetings", "responses": { "200": text("Meetings page", "text/html") } },
+            },
+            "/meetings/{date}": {
+                "get": {
+                    "summary": "Talks presented at a past meeting",
+                    "parameters": [{ "name": "date", "in": "path", "required": true, "schema": date }],
+                    "responses": { "200": text("Meeting page", "text/html"), "404": error("No such meeting") },
+                },
+            },
+            "/minutes.md": {
+                "get": { "summary": "Meeting minutes for the visible talks", "responses": { "200": text("Minutes", "text/markdown") } },
+            },
+            "/minutes/{date}.md": {
+                "get": {
+                    "summary": "Meeting minutes for a past meeting",
+                    "parameters": [{ "name": "date", "in": "path", "required": true, "schema": date }],
+                    "responses": { "200": text("Minutes", "text/markdown"), "404": error("No such meeting") },
+                },
+            },
+        },
+        "components": { "schemas": gen.take_definitions() },
+    })
+}
+
+// One json schema per websocket event variant, keyed by file name
+pub fn event_schemas() -> Vec<(String, Value)> {
+    let mut schemas = variant_schemas::<EventRequest>("request");
+    schemas.extend(variant_schemas::<EventResponse>("response"));
+    schemas
+}
+
+fn variant_schemas<T: JsonSchema>(direction: &str) -> Vec<(String, Value)> {
+    let mut gen = SchemaGenerator::new(SchemaSettings::draft07().with(|settings| {
+        settings.inline_subschemas = true;
+    }));
+    let root = gen.root_schema_for::<T>();
+
+    root.schema.subschemas
+        .and_then(|subschemas| subschemas.one_of)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|variant| {
+            let mut schema = serde_json::to_value(variant).ok()?;
+            let name = schema["properties"]["event"]["enum"][0].as_str()?.to_string();
+
+            schema["$schema"] = json!(JSON_SCHEMA_DRAFT);
+            schema["title"] = json!(name);
+            Some((format!("{}/{}.json", direction, name), schema))
+        })
+        .collect()
+}
+
+fn query(name: &str, schema: Value, description: &str) -> Value {
+    json!({ "name": name, "in": "query", "required": false, "description": description, "schema": schema })
+}
+
+fn body(schema: &Schema) -> Value {
+    json!({ "required": true, "content": { "application/json": { "schema": schema } } })
+}
+
+fn ok(description: &str, schema: &Schema) -> Value {
+    json!({ "description": description, "content": { "application/json": { "schema": schema } } })
+}
+
+fn text(description: &str, content_type: &str) -> Value {
+    json!({ "description": description, "content": { content_type: { "schema": { "type": "string" } } } })
+}
+
+fn error(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ErrorResponse" } } },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, env, fs, path::{Path, PathBuf}};
+
+    use serde_json::Value;
+
+    use super::{event_schemas, openapi};
+
+    fn schemas_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas")
+    }
+
+    // Run `UPDATE_SCHEMAS=1 cargo test` to regenerate the files after changing the events
+    #[test]
+    fn event_schemas_match_the_committed_files() {
+        let dir = schemas_dir();
+        let update = env::var_os("UPDATE_SCHEMAS").is_some();
+
+        for (name, schema) in event_schemas() {
+            let path = dir.join(&name);
+            let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+
+            if update {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, generated).unwrap();
+                continue;
+            }
+
+            let committed = fs::read_to_string(&path)
+                .unwrap_or_else(|_| panic!("{} is missing, run UPDATE_SCHEMAS=1 cargo test", path.display()));
+            assert_eq!(committed, generated, "{} is out of date, run UPDATE_SCHEMAS=1 cargo test", path.display());
+        }
+    }
+
+    #[test]
+    fn no_schemas_for_removed_events() {
+        let generated: HashSet<String> = event_schemas().into_iter().map(|(name, _)| name).collect();
+
+        for direction in &["request", "response"] {
+            for entry in fs::read_dir(schemas_dir().join(direction)).unwrap() {
+                let name = format!("{}/{}", direction, entry.unwrap().file_name().to_string_lossy());
+                assert!(generated.contains(&name), "schemas/{} has no matching event", name);
+            }
+        }
+    }
+
+    #[test]
+    fn openapi_references_resolve() {
+        fn check(value: &Value, components: &Value) {
+            match value {
+                Value::Object(object) => {
+                    if let Some(Value::String(reference)) = object.get("$ref") {
+                        let name = reference.trim_start_matches("#/components/schemas/");
+                        assert!(components.get(name).is_some(), "{} is not defined", reference);
+                    }
+                    object.values().for_each(|value| check(value, components));
+                }
+                Value::Array(array) => array.iter().for_each(|value| check(value, components)),
+                _ => {}
+            }
+        }
+
+        let document = openapi();
+        check(&document, &document["components"]["schemas"]);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::model::{Presenter, PresenterSummary};
+
+// How many presenters are suggested for a name
+const MAX_SUGGESTIONS: usize = 5;
+
+// Trims the name and collapses runs of whitespace, presenter names are stored this way
+pub fn tidy_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Name shown for a talk given by several presenters, e.g. "Alice, Bob & Carol"
+pub fn joined_names(presenters: &[Presenter]) -> String {
+    let names: Vec<&str> = presenters.iter().map(|presenter| presenter.name.as_str()).collect();
+    join_names(&names)
+}
+
+pub fn join_names<S: AsRef<str>>(names: &[S]) -> String {
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => {
+            let rest: Vec<&str> = rest.iter().map(AsRef::as_ref).collect();
+            format!("{} & {}", rest.join(", "), last.as_ref())
+        }
+        Some((last, _)) => last.as_ref().to_string(),
+        None => String::new(),
+    }
+}
+
+// Presenters whose names look like `name`, closest first
+pub fn suggest(name: &str, presenters: Vec<PresenterSummary>) -> Vec<PresenterSummary> {
+    let mut matches: Vec<(usize, PresenterSummary)> = presenters.into_iter()
+        .filter_map(|presenter| distance(name, &presenter.name).map(|distance| (distance, presenter)))
+        .collect();
+
+    // Among equally close names prefer the people who gave more talks
+    matches.sort_by(|(a, first), (b, second)| {
+        a.cmp(b)
+            .then(second.talks.cmp(&first.talks))
+            .then_with(|| first.name.cmp(&second.name))
+    });
+
+    matches.into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, presenter)| presenter)
+        .collect()
+}
+
+// How far apart two names are, None when they probably belong to different people
+//
+// "cole m" is close to "Cole Mahoney" since every word of the shorter name
+// starts a word of the longer one. Small typos are tolerated as well.
+fn distance(a: &str, b: &str) -> Option<usize> {
+    let a = words(a);
+    let b = words(b);
+
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    if a == b {
+        return Some(0);
+    }
+
+    let (short, long) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    if abbreviates(short, long) {
+        return Some(1);
+    }
+
+    // Allow one typo for every four characters
+    let a = a.join(" ");
+    let b = b.join(" ");
+    let typos = edit_distance(&a, &b);
+    if typos <= a.chars().count().min(b.chars().count()) / 4 {
+        Some(1 + typos)
+    } else {
+        None
+    }
+}
+
+fn words(name: &str) -> Vec<String> {
+    name.split_whitespace().map(str::to_lowercase).collect()
+}
+
+// Whether every word of `short` starts a word of `long`, in the same order
+fn abbreviates(short: &[String], long: &[String]) -> bool {
+    let mut long = long.iter();
+    short.iter().all(|word| long.any(|candidate| candidate.starts_with(word.as_str())))
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &y) in b.iter().enumerate() {
+            let substitution = previous[j] + if x == y { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,88 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Link, Talk, TalkRevision, TalkType};
+
+// A field that differs between two versions of a talk, shown as text
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+// A revision and the fields the version after it changed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct RevisionChanges {
+    pub revision: TalkRevision,
+    pub changes: Vec<FieldChange>,
+}
+
+// The fields of a talk that are edited, as text
+struct Version {
+    presenters: String,
+    talk_type: String,
+    description: String,
+    duration: String,
+    links: String,
+    tags: String,
+}
+
+impl Version {
+    fn of_talk(talk: &Talk) -> Version {
+        let presenters: Vec<String> = talk.presenters.iter().map(|presenter| presenter.name.clone()).collect();
+        Version::new(&presenters, talk.talk_type.label.clone(), &talk.description, talk.duration, &talk.links, &talk.tags)
+    }
+
+    fn of_revision(revision: &TalkRevision, talk_types: &[TalkType]) -> Version {
+        let talk_type = talk_types.iter()
+            .find(|talk_type| talk_type.id == revision.talk_type)
+            .map(|talk_type| talk_type.label.clone())
+            .unwrap_or_else(|| format!("deleted type {}", revision.talk_type));
+        Version::new(&revision.presenters, talk_type, &revision.description, revision.duration, &revision.links, &revision.tags)
+    }
+
+    fn new(presenters: &[String], talk_type: String, description: &str, duration: Option<i32>, links: &[Link], tags: &[String]) -> Version {
+        Version {
+            presenters: presenters.join(", "),
+            talk_type,
+            description: description.to_string(),
+            duration: duration.map_or_else(|| "default".to_string(), |minutes| format!("{} minutes", minutes)),
+            links: links.iter().map(|link| format!("{}: {}", link.kind, link.url)).collect::<Vec<String>>().join("\n"),
+            tags: tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<String>>().join(" "),
+        }
+    }
+
+    // The fields that differ in `newer`
+    fn changes(&self, newer: &Version) -> Vec<FieldChange> {
+        let fields = [
+            ("presenters", &self.presenters, &newer.presenters),
+            ("type", &self.talk_type, &newer.talk_type),
+            ("description", &self.description, &newer.description),
+            ("duration", &self.duration, &newer.duration),
+            ("links", &self.links, &newer.links),
+            ("tags", &self.tags, &newer.tags),
+        ];
+
+        fields.iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|(field, before, after)| FieldChange { field: field.to_string(), before: before.to_string(), after: after.to_string() })
+            .collect()
+    }
+}
+
+// Pairs every revision of a talk, newest first, with what the next version changed
+//
+// The newest revision is compared with the talk as it is now.
+pub fn history(talk: &Talk, revisions: Vec<TalkRevision>, talk_types: &[TalkType]) -> Vec<RevisionChanges> {
+    let mut newer = Version::of_talk(talk);
+
+    revisions.into_iter()
+        .map(|revision| {
+            let older = Version::of_revision(&revision, talk_types);
+            let changes = older.changes(&newer);
+            newer = older;
+            RevisionChanges { revision, changes }
+        })
+        .collect()
+}
@@ -1,9 +1,131 @@
+table! {
+    attachments (id) {
+        id -> Integer,
+        talk_id -> Integer,
+        file_name -> Text,
+        content_type -> Text,
+        size -> BigInt,
+        sha256 -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    comments (id) {
+        id -> Integer,
+        talk_id -> Integer,
+        author -> Text,
+        body -> Text,
+        created_at -> Timestamp,
+        is_visible -> Bool,
+    }
+}
+
+table! {
+    links (talk_id, position) {
+        talk_id -> Integer,
+        position -> Integer,
+        kind -> Integer,
+        url -> Text,
+    }
+}
+
+table! {
+    presenters (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+table! {
+    tags (talk_id, tag) {
+        talk_id -> Integer,
+        tag -> Text,
+    }
+}
+
+table! {
+    talk_presenters (talk_id, presenter_id) {
+        talk_id -> Integer,
+        presenter_id -> Integer,
+        position -> Integer,
+    }
+}
+
+table! {
+    talk_revisions (id) {
+        id -> Integer,
+        talk_id -> Integer,
+        name -> Text,
+        talk_type -> Integer,
+        description -> Text,
+        duration -> Nullable<Integer>,
+        presenters -> Text,
+        links -> Text,
+        tags -> Text,
+        replaced_at -> Timestamp,
+    }
+}
+
+table! {
+    talk_types (id) {
+        id -> Integer,
+        slug -> Text,
+        label -> Text,
+        heading -> Text,
+        position -> Integer,
+        color -> Text,
+        duration -> Integer,
+        accepts_votes -> Bool,
+        after_meeting -> Bool,
+    }
+}
+
 table! {
     talks (id) {
         id -> Integer,
         name -> Text,
         talk_type -> Integer,
         description -> Text,
-        is_visible -> Bool,
+        meeting -> Nullable<Date>,
+        duration -> Nullable<Integer>,
+        started_at -> Nullable<Timestamp>,
+        presented_at -> Nullable<Timestamp>,
+        votes -> Integer,
+        status -> Integer,
+        submitted_by -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        hidden_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    votes (talk_id, client_id) {
+        talk_id -> Integer,
+        client_id -> Text,
     }
 }
+
+joinable!(attachments -> talks (talk_id));
+joinable!(comments -> talks (talk_id));
+joinable!(links -> talks (talk_id));
+joinable!(tags -> talks (talk_id));
+joinable!(talk_presenters -> presenters (presenter_id));
+joinable!(talk_presenters -> talks (talk_id));
+joinable!(talk_revisions -> talks (talk_id));
+joinable!(talks -> talk_types (talk_type));
+joinable!(votes -> talks (talk_id));
+
+allow_tables_to_appear_in_same_query!(
+    attachments,
+    comments,
+    links,
+    presenters,
+    tags,
+    talk_presenters,
+    talk_revisions,
+    talk_types,
+    talks,
+    votes,
+);
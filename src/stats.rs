@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use chrono::{Datelike, NaiveDate};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{db::DBManager, error::AppError};
+
+// How many presenters are listed in the statistics
+const TOP_PRESENTERS: i64 = 15;
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct Count {
+    pub label: String,
+    pub talks: i64,
+}
+
+// Talks presented during one semester
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct SemesterStats {
+    pub semester: String,
+    pub meetings: i64,
+    pub talks: i64,
+    // One count per talk type, in meeting order
+    pub by_type: Vec<Count>,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct Stats {
+    pub talks: i64,
+    pub meetings: i64,
+    pub by_type: Vec<Count>,
+    pub by_presenter: Vec<Count>,
+    // Oldest semester first
+    pub by_semester: Vec<SemesterStats>,
+}
+
+// Clarkson's semesters, in the order they happen during a year
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Term {
+    Spring,
+    Summer,
+    Fall,
+}
+
+fn semester(date: NaiveDate) -> (i32, Term) {
+    let term = match date.month() {
+        1..=5 => Term::Spring,
+        6..=8 => Term::Summer,
+        _ => Term::Fall,
+    };
+
+    (date.year(), term)
+}
+
+pub fn collect(db: &DBManager) -> Result<Stats, AppError> {
+    let talk_types = db.list_talk_types()?;
+    let type_counts = db.count_talks_by_type()?;
+    let by_type: Vec<Count> = talk_types.iter()
+        .map(|talk_type| Count {
+            label: talk_type.label.clone(),
+            talks: type_counts.iter().find(|(kind, _)| *kind == talk_type.id).map_or(0, |(_, talks)| *talks),
+        })
+        .collect();
+
+    let by_presenter = db.count_talks_by_presenter(TOP_PRESENTERS)?
+        .into_iter()
+        .map(|(label, talks)| Count { label, talks })
+        .collect();
+
+    // Group the archived talks by semester, then by meeting and type
+    let mut semesters: BTreeMap<(i32, Term), BTreeMap<NaiveDate, Vec<i32>>> = BTreeMap::new();
+    for (date, talk_type) in db.list_archived_talk_types()? {
+        semesters.entry(semester(date)).or_default().entry(date).or_default().push(talk_type);
+    }
+
+    let by_semester: Vec<SemesterStats> = semesters.into_iter()
+        .map(|((year, term), meetings)| {
+            let types: Vec<i32> = meetings.values().flatten().copied().collect();
+
+            SemesterStats {
+                semester: format!("{:?} {}", term, year),
+                meetings: meetings.len() as i64,
+                talks: types.len() as i64,
+                by_type: talk_types.iter()
+                    .map(|talk_type| Count {
+                        label: talk_type.label.clone(),
+                        talks: types.iter().filter(|&&kind| kind == talk_type.id).count() as i64,
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(Stats {
+        talks: by_type.iter().map(|count| count.talks).sum(),
+        meetings: by_semester.iter().map(|semester| semester.meetings).sum(),
+        by_type,
+        by_presenter,
+        by_semester,
+    })
+}
+
+// Renders a horizontal bar chart as an inline svg
+pub fn bar_chart(counts: &[Count]) -> String {
+    const WIDTH: i64 = 640;
+    const LABEL_WIDTH: i64 = 200;
+    const BAR_HEIGHT: i64 = 24;
+    const GAP: i64 = 6;
+
+    let max = counts.iter().map(|count| count.talks).max().unwrap_or(0).max(1);
+    let height = counts.len() as i64 * (BAR_HEIGHT + GAP) + GAP;
+    let scale = (WIDTH - LABEL_WIDTH - 40) as f64 / max as f64;
+
+    let mut svg = String::new();
+    let _ = write!(svg,
+        r#"<svg class="chart" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+        WIDTH, height, WIDTH, height);
+
+    for (i, count) in counts.iter().enumerate() {
+        let y = GAP + i as i64 * (BAR_HEIGHT + GAP);
+        let width = (count.talks as f64 * scale).round() as i64;
+        let _ = write!(svg,
+            r#"<text x="{}" y="{}" text-anchor="end">{}</text><rect x="{}" y="{}" width="{}" height="{}"></rect><text x="{}" y="{}">{}</text>"#,
+            LABEL_WIDTH - 8, y + BAR_HEIGHT - 7, escape_xml(&count.label),
+            LABEL_WIDTH, y, width, BAR_HEIGHT,
+            LABEL_WIDTH + width + 6, y + BAR_HEIGHT - 7, count.talks);
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,42 @@
+use crate::model::TagSummary;
+
+// Sizes of the tag cloud, the most used tag is shown in the largest
+const CLOUD_SIZES: f64 = 5.0;
+
+// A tag in the tag cloud, `size` goes from 1 for the least used tags to 5
+pub struct CloudTag {
+    pub tag: String,
+    pub talks: i64,
+    pub size: i64,
+}
+
+// Lowercases the tag and joins its words with dashes, tags are stored this way
+//
+// "Open Source" becomes "open-source" and "#Rust" becomes "rust".
+pub fn tidy_tag(tag: &str) -> String {
+    tag.trim()
+        .trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("-")
+        .to_lowercase()
+}
+
+// Whether a tidy tag can be used as is in urls, e.g. "rust", "c++" or "3d-printing"
+pub fn is_valid(tag: &str) -> bool {
+    tag.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+}
+
+// Sizes every tag by how many talks have it, on a log scale so a few popular
+// tags do not shrink all the others
+pub fn cloud(tags: Vec<TagSummary>) -> Vec<CloudTag> {
+    let most = tags.iter().map(|tag| tag.talks).max().unwrap_or(1).max(1) as f64;
+
+    tags.into_iter()
+        .map(|tag| {
+            let weight = if most > 1.0 { (tag.talks.max(1) as f64).ln() / most.ln() } else { 1.0 };
+            CloudTag { size: 1 + (weight * (CLOUD_SIZES - 1.0)).round() as i64, tag: tag.tag, talks: tag.talks }
+        })
+        .collect()
+}
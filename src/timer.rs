@@ -0,0 +1,137 @@
+use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{Clients, events::{EventResponse, send_events}, model::Talk};
+
+// Timer type
+pub type SharedTimer = Arc<Mutex<Timer>>;
+
+pub fn create_timer() -> SharedTimer {
+    Arc::new(Mutex::new(Timer::new()))
+}
+
+// Seconds left for the talk being timed, negative once it runs over
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct TimerState {
+    pub talk: Option<i32>,
+    pub length: i64,
+    pub remaining: i64,
+    pub running: bool,
+}
+
+// Counts down the talk being presented, every client is sent the same time
+#[derive(Debug)]
+pub struct Timer {
+    talk: Option<i32>,
+    // Time the talk may take, extending the timer adds to it
+    length: Duration,
+    // Time counted before the timer was last paused
+    elapsed: Duration,
+    // When the timer was last started, None while paused
+    resumed: Option<Instant>,
+    // Whether the overrun notice went out for this talk
+    overrun_sent: bool,
+}
+
+impl Timer {
+    fn new() -> Timer {
+        Timer { talk: None, length: Duration::from_secs(0), elapsed: Duration::from_secs(0), resumed: None, overrun_sent: false }
+    }
+
+    pub fn talk(&self) -> Option<i32> {
+        self.talk
+    }
+
+    // Times `talk` from the start, or stops timing when nothing is presented
+    pub fn reset(&mut self, talk: Option<&Talk>) {
+        let minutes = talk.map_or(0, Talk::minutes);
+
+        self.talk = talk.map(|talk| talk.id);
+        self.length = Duration::from_secs(minutes as u64 * 60);
+        self.elapsed = Duration::from_secs(0);
+        self.resumed = talk.map(|_| Instant::now());
+        self.overrun_sent = false;
+    }
+
+    pub fn start(&mut self) {
+        if self.talk.is_some() && self.resumed.is_none() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(resumed) = self.resumed.take() {
+            self.elapsed += resumed.elapsed();
+        }
+    }
+
+    pub fn extend(&mut self, seconds: u64) {
+        self.length += Duration::from_secs(seconds);
+
+        // Warn again if the extra time runs out too
+        if self.remaining() > 0 {
+            self.overrun_sent = false;
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.resumed.is_some()
+    }
+
+    // Whole seconds left, negative once the talk runs over
+    pub fn remaining(&self) -> i64 {
+        let elapsed = self.elapsed + self.resumed.map_or(Duration::from_secs(0), |resumed| resumed.elapsed());
+        let length = self.length.as_secs() as i64;
+        let elapsed = elapsed.as_secs() as i64;
+        length - elapsed
+    }
+
+    pub fn state(&self) -> TimerState {
+        TimerState {
+            talk: self.talk,
+            length: self.length.as_secs() as i64,
+            remaining: self.remaining(),
+            running: self.is_running(),
+        }
+    }
+
+    pub fn response(&self) -> EventResponse {
+        let TimerState { talk, length, remaining, running } = self.state();
+        EventResponse::Timer { talk, length, remaining, running }
+    }
+
+    // Events to send every second, nothing while the timer is paused
+    fn tick(&mut self) -> Vec<EventResponse> {
+        let mut events = Vec::new();
+        if !self.is_running() {
+            return events;
+        }
+
+        events.push(self.response());
+        if let Some(talk) = self.talk {
+            if self.remaining() <= 0 && !self.overrun_sent {
+                self.overrun_sent = true;
+                events.push(EventResponse::Overrun { talk });
+            }
+        }
+
+        events
+    }
+}
+
+// Forever send the time left to every client once a second
+pub async fn run_timer(timer: SharedTimer, clients: Clients) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+
+        // The lock is released before sending, clients may be slow
+        let events = timer.lock().unwrap().tick();
+        for event in events {
+            send_events(clients.clone(), event).await;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::env;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{error::{AppError, ErrorType}, presenters::tidy_name};
+
+// Longest text of each kind a client may send, in characters, and how many tags and links a talk may have
+#[derive(Debug, Clone)]
+pub struct Limits {
+    // Presenter names and comment authors
+    pub name: usize,
+    pub description: usize,
+    pub comment: usize,
+    // Reasons for rejecting a proposed talk
+    pub reason: usize,
+    // Talk type slugs, labels and headings
+    pub type_name: usize,
+    pub tag: usize,
+    pub url: usize,
+    pub tags: usize,
+    pub links: usize,
+}
+
+impl Limits {
+    pub fn from_env() -> Limits {
+        Limits {
+            name: positive("MAX_NAME_LENGTH", 100),
+            description: positive("MAX_DESCRIPTION_LENGTH", 10000),
+            comment: positive("MAX_COMMENT_LENGTH", 2000),
+            reason: positive("MAX_REASON_LENGTH", 500),
+            type_name: positive("MAX_TYPE_NAME_LENGTH", 40),
+            tag: positive("MAX_TAG_LENGTH", 30),
+            url: positive("MAX_LINK_LENGTH", 2048),
+            tags: positive("MAX_TAGS", 10),
+            links: positive("MAX_LINKS", 10),
+        }
+    }
+}
+
+// Reads a limit from the environment
+fn positive(variable: &str, default: usize) -> usize {
+    match env::var(variable) {
+        Ok(limit) => {
+            limit.parse().ok()
+                .filter(|limit| *limit > 0)
+                .unwrap_or_else(|| panic!("{} must be a positive number", variable))
+        }
+        Err(_) => {
+            default
+        }
+    }
+}
+
+// A name or label on a single line, whitespace is collapsed like presenter names
+pub fn line(field: &str, value: &str, max: usize) -> Result<String, AppError> {
+    let value: String = value.nfc().collect();
+    if value.chars().any(char::is_control) {
+        return Err(bad_request(&format!("{} cannot contain control characters or line breaks", field)));
+    }
+
+    check(field, tidy_name(&value), max)
+}
+
+// Text that may span several lines, such as descriptions and comments
+//
+// Line breaks are stored as \n and tabs are kept, other control characters are refused.
+pub fn text(field: &str, value: &str, max: usize) -> Result<String, AppError> {
+    let value: String = value.replace("\r\n", "\n").nfc().collect();
+    if value.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        return Err(bad_request(&format!("{} cannot contain control characters", field)));
+    }
+
+    check(field, value.trim().to_string(), max)
+}
+
+// Like `text`, but a blank value means there is none
+pub fn optional_text(field: &str, value: Option<String>, max: usize) -> Result<Option<String>, AppError> {
+    match value {
+        Some(value) if !value.trim().is_empty() => Ok(Some(text(field, &value, max)?)),
+        _ => Ok(None),
+    }
+}
+
+// A list such as the tags of a talk, `field` names what is in it
+pub fn count<T>(field: &str, items: &[T], max: usize) -> Result<(), AppError> {
+    if items.len() > max {
+        return Err(bad_request(&format!("a talk may have at most {} {}, this one has {}", max, field, items.len())));
+    }
+
+    Ok(())
+}
+
+fn check(field: &str, value: String, max: usize) -> Result<String, AppError> {
+    if value.is_empty() {
+        return Err(bad_request(&format!("{} cannot be blank", field)));
+    }
+
+    let length = value.chars().count();
+    if length > max {
+        return Err(bad_request(&format!("{} is limited to {} characters, this one has {}", field, max, length)));
+    }
+
+    Ok(value)
+}
+
+fn bad_request(message: &str) -> AppError {
+    AppError::new(message, ErrorType::BadRequest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count, line, optional_text, text};
+
+    #[test]
+    fn text_is_normalized_to_nfc() {
+        // "e" followed by a combining acute accent becomes a single "é"
+        assert_eq!(line("name", "Jose\u{301}", 10).unwrap(), "Jos\u{e9}");
+        assert_eq!(text("description", "cafe\u{301}", 10).unwrap(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn text_is_trimmed() {
+        assert_eq!(line("name", "  Alice   Bob ", 20).unwrap(), "Alice Bob");
+        assert_eq!(text("description", "\n  first\r\n\tsecond  \n", 20).unwrap(), "first\n\tsecond");
+    }
+
+    #[test]
+    fn control_characters_are_refused() {
+        for value in &["Bob\u{7}", "Alice\nBob", "tab\there", "null\u{0}"] {
+            assert!(line("name", value, 20).is_err(), "{:?}", value);
+        }
+        for value in &["bell\u{7}", "null\u{0}", "escape\u{1b}[31m", "delete\u{7f}"] {
+            assert!(text("description", value, 20).is_err(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn line_breaks_and_tabs_are_kept_in_text() {
+        assert_eq!(text("description", "one\ntwo\tthree", 20).unwrap(), "one\ntwo\tthree");
+    }
+
+    #[test]
+    fn blank_fields_are_refused() {
+        for value in &["", "   ", "\u{a0}"] {
+            assert_eq!(line("name", value, 20).unwrap_err().message, "name cannot be blank", "{:?}", value);
+        }
+        for value in &["", "   ", "\u{a0}", "\n\t\n"] {
+            assert_eq!(text("description", value, 20).unwrap_err().message, "description cannot be blank", "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn length_limits_are_counted_in_characters() {
+        assert_eq!(line("name", "ééééé", 5).unwrap(), "ééééé");
+        assert_eq!(line("name", "ééééé!", 5).unwrap_err().message, "name is limited to 5 characters, this one has 6");
+        // Surrounding whitespace does not count
+        assert!(text("description", "  abc  ", 3).is_ok());
+    }
+
+    #[test]
+    fn optional_text_may_be_blank() {
+        assert_eq!(optional_text("reason", None, 10).unwrap(), None);
+        assert_eq!(optional_text("reason", Some(String::from("  ")), 10).unwrap(), None);
+        assert_eq!(optional_text("reason", Some(String::from(" spam ")), 10).unwrap(), Some(String::from("spam")));
+        assert!(optional_text("reason", Some(String::from("far too long")), 5).is_err());
+    }
+
+    #[test]
+    fn lists_are_limited() {
+        assert!(count("tags", &["rust"; 3], 3).is_ok());
+        assert_eq!(count("tags", &["rust"; 4], 3).unwrap_err().message, "a talk may have at most 3 tags, this one has 4");
+    }
+}
@@ -0,0 +1,53 @@
+var clientID = null;
+
+window.onload = function () {
+    // Register a client so reverts can be authenticated
+    fetch("/register")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (result) {
+            clientID = result.id;
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+};
+
+// Put the talk back the way it was at a revision, the admin password is needed every time
+function revert(revision) {
+    let talk = document.body.dataset.talk;
+
+    if (!clientID) {
+        return;
+    }
+
+    let password = prompt("Please enter the admin password.");
+    if (!password) {
+        return;
+    }
+
+    fetch("/authenticate", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ "id": clientID, "password": password }),
+    })
+        .then(function () {
+            return fetch("/api/v1/talks/" + talk + "/revisions/" + revision + "/revert", {
+                method: "POST",
+                headers: { "x-client-id": clientID },
+            });
+        })
+        .then(function (response) {
+            if (response.ok) {
+                window.location.reload();
+            } else {
+                return response.json().then(function (result) {
+                    alert(result.error);
+                });
+            }
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
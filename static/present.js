@@ -0,0 +1,121 @@
+var websocket = null;
+
+window.onload = function () {
+    connect();
+    refresh();
+};
+
+function connect() {
+    // The projector only listens, it never authenticates
+    fetch("/register")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (result) {
+            let protocol = window.location.protocol[4] == 's' ? "wss://" : "ws://";
+            websocket = new WebSocket(protocol + window.location.host + "/ws/" + result.id);
+
+            // The timer ticks every second, any other event can change the running order
+            websocket.onmessage = function (event) {
+                let json = JSON.parse(event.data);
+
+                if (json.event == "Timer") {
+                    showTimer(json);
+                } else if (json.event == "Overrun") {
+                    document.getElementById("countdown").className = "countdown overrun";
+                } else {
+                    refresh();
+                }
+            };
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Reconnect when the websocket closes
+setInterval(function () {
+    if (websocket && websocket.readyState != WebSocket.OPEN && websocket.readyState != WebSocket.CONNECTING) {
+        connect();
+        refresh();
+    }
+}, 10000);
+
+function refresh() {
+    fetch("/api/v1/agenda")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(showAgenda)
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+
+    fetch("/api/v1/timer")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(showTimer)
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+function showAgenda(agenda) {
+    let current = agenda.items.find(item => item.talk.id == agenda.current);
+    let next = agenda.items.find(item => item.talk.id == agenda.next);
+
+    document.getElementById("current-name").innerText = current ? current.talk.name : "Nobody yet";
+    // Descriptions are rendered and sanitized by the server
+    document.getElementById("current-description").innerHTML = current ? current.talk.description_html : "";
+    document.getElementById("next").innerText = next ? "Next: " + next.talk.name + " · " + next.talk.type.label : "";
+
+    let rows = document.getElementById("order");
+    rows.innerHTML = "";
+
+    agenda.items.forEach(item => {
+        let row = rows.insertRow();
+
+        if (item.talk.id == agenda.current) {
+            row.setAttribute("class", "event current");
+        } else if (item.talk.presented_at) {
+            row.setAttribute("class", "event presented");
+        } else {
+            row.setAttribute("class", "event");
+        }
+
+        let cells = [item.start.substring(0, 5), item.talk.name, item.talk.type.label, null, item.minutes];
+        let classes = ["", "name", "type", "description", ""];
+        cells.forEach((value, i) => {
+            let cell = row.insertCell();
+            if (value === null) {
+                cell.innerHTML = item.talk.description_html;
+            } else {
+                cell.innerText = value;
+            }
+            if (classes[i]) {
+                cell.setAttribute("class", classes[i]);
+            }
+            if (classes[i] == "type") {
+                cell.style.borderLeftColor = item.talk.type.color;
+            }
+        });
+    });
+}
+
+// Show the time left for the current talk, as counted by the server
+function showTimer(timer) {
+    let countdown = document.getElementById("countdown");
+
+    if (timer.talk == null) {
+        countdown.innerText = "--:--";
+        countdown.className = "countdown";
+        return;
+    }
+
+    let seconds = Math.abs(timer.remaining);
+    let text = Math.floor(seconds / 60) + ":" + String(seconds % 60).padStart(2, "0");
+
+    countdown.innerText = timer.remaining < 0 ? "-" + text : text;
+    countdown.className = timer.remaining < 0 ? "countdown overrun" : timer.running ? "countdown" : "countdown paused";
+}
@@ -0,0 +1,55 @@
+var clientID = null;
+
+window.onload = function () {
+    // Register a client so merges can be authenticated
+    fetch("/register")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (result) {
+            clientID = result.id;
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+};
+
+// Ask to merge the selected presenters, the admin password is needed every time
+function merge() {
+    let from = document.getElementById("from");
+    let into = document.getElementById("into");
+
+    if (!clientID || !from || !into || from.value == into.value) {
+        return;
+    }
+
+    let password = prompt("Please enter the admin password.");
+    if (!password) {
+        return;
+    }
+
+    fetch("/authenticate", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ "id": clientID, "password": password }),
+    })
+        .then(function () {
+            return fetch("/api/v1/presenters/merge", {
+                method: "POST",
+                headers: { "Content-Type": "application/json", "x-client-id": clientID },
+                body: JSON.stringify({ "from": parseInt(from.value), "into": parseInt(into.value) }),
+            });
+        })
+        .then(function (response) {
+            return response.json().then(function (result) {
+                if (response.ok) {
+                    window.location = "/presenters/" + result.presenter.id;
+                } else {
+                    alert(result.error);
+                }
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
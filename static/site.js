@@ -25,9 +25,9 @@ function auth() {
     return authenticated;
 }
 
-// Ask to create an entry
+// Ask to create an entry, without the password it is proposed to the moderators
 function create() {
-    if (!auth()) {
+    if (!auth() && !confirm("Without the meeting password your talk waits for a moderator. Propose it?")) {
         return
     }
 
@@ -35,6 +35,9 @@ function create() {
     let name = document.getElementById("name");
     let type = document.getElementById("type");
     let desc = document.getElementById("description");
+    let duration = document.getElementById("duration");
+    let links = document.getElementById("links");
+    let tags = document.getElementById("tags");
 
     // Check for errors
     if (!name || !type || !desc) {
@@ -44,11 +47,27 @@ function create() {
     // Create event
     let event = {
         "event": "Create",
-        "name": name.value,
+        "presenters": splitPresenters(name.value),
         "talk_type": type.value,
         "desc": desc.value,
     };
 
+    // Leave the duration out to use the default for the talk type
+    if (duration && duration.value) {
+        event["duration"] = parseInt(duration.value);
+        duration.value = "";
+    }
+
+    if (links && links.value.trim()) {
+        event["links"] = splitLinks(links.value);
+        links.value = "";
+    }
+
+    if (tags && tags.value.trim()) {
+        event["tags"] = splitTags(tags.value);
+        tags.value = "";
+    }
+
     name.value = ""
     type.value = ""
     desc.value = ""
@@ -59,6 +78,119 @@ function create() {
     websocket.send(JSON.stringify(event));
 }
 
+// Admins present the talks in order during the meeting and time them
+function moderate(action, fields) {
+    if (!becomeAdmin()) {
+        return;
+    }
+
+    checkAndReset().await;
+    websocket.send(JSON.stringify(Object.assign({ "event": action }, fields)));
+}
+
+function becomeAdmin() {
+    if (!admin) {
+        let password = prompt("Please enter the admin password.");
+
+        // synchronous request to /authenticate
+        var xhttp = new XMLHttpRequest();
+        xhttp.open("POST", "/authenticate", false);
+        xhttp.setRequestHeader("Content-Type", "application/json");
+        xhttp.send(JSON.stringify({ "id": wsID, "password": password }));
+
+        if (xhttp.status == 200) {
+            let result = JSON.parse(xhttp.responseText);
+            authenticated = result.authenticated;
+            admin = result.admin;
+        }
+    }
+
+    return admin;
+}
+
+// Show the talks proposed by visitors, new proposals are added as they arrive
+function showProposals() {
+    if (!becomeAdmin()) {
+        return;
+    }
+
+    fetch("/api/v1/proposals", { headers: { "x-client-id": wsID } })
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (talks) {
+            document.getElementById("proposal-rows").innerHTML = "";
+            document.getElementById("proposals").hidden = false;
+            talks.forEach(addProposal);
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+function addProposal(talk) {
+    let row = document.createElement("tr");
+    row.setAttribute("class", "event");
+    row.dataset.id = talk.id;
+
+    let name = row.insertCell(0);
+    name.setAttribute("class", "name");
+    name.innerText = talk.name;
+
+    let type = row.insertCell(1);
+    type.setAttribute("class", "type");
+    type.innerText = talk.type.label;
+    type.style.borderLeftColor = talk.type.color;
+
+    // The description is rendered and sanitized by the server
+    let desc = row.insertCell(2);
+    desc.setAttribute("class", "desc");
+    desc.innerHTML = talk.description_html;
+
+    let actions = row.insertCell(3);
+    actions.setAttribute("class", "actions");
+    actions.innerHTML = '<button onclick="moderate(\'Approve\', { \'id\': ' + talk.id + ' })"> approve </button> <button onclick="reject(' + talk.id + ')"> reject </button>';
+
+    document.getElementById("proposal-rows").appendChild(row);
+}
+
+function removeProposal(id) {
+    let row = document.querySelector('#proposal-rows tr[data-id="' + id + '"]');
+    if (row) {
+        row.remove();
+    }
+}
+
+// The visitor who proposed the talk is sent the reason
+function reject(id) {
+    let reason = prompt("Why is this talk rejected? (optional)");
+    if (reason === null) {
+        return;
+    }
+
+    moderate("Reject", { "id": id, "reason": reason });
+}
+
+// Vote for a forum topic, every client may vote for a topic once
+function vote(id) {
+    if (!auth()) {
+        return
+    }
+
+    checkAndReset().await;
+    websocket.send(JSON.stringify({ "event": "Vote", "id": id }));
+}
+
+// Take back a talk that was not presented yet
+function withdraw(id) {
+    if (!auth()) {
+        return
+    }
+
+    checkAndReset().await;
+    websocket.send(JSON.stringify({ "event": "SetStatus", "id": id, "status": "withdrawn" }));
+}
+
 // Ask to hide an entry
 function hide(id) {
     if (!auth()) {
@@ -78,6 +210,7 @@ function hide(id) {
 var websocket = null;
 var wsID = null;
 var authenticated = false;
+var admin = false;
 
 window.onload = function () {
     // Tie pressing enter on the description field to the create button
@@ -92,9 +225,65 @@ window.onload = function () {
             }
         }, false);
 
+    // Suggest existing presenters while a name is typed
+    document.getElementById("name").addEventListener("input", suggestPresenters, false);
+
     register();
+    refreshTimer();
 };
 
+// Links are separated by spaces, their kind is guessed from the url
+function splitLinks(urls) {
+    return urls.split(/\s+/).filter(url => url.length > 0).map(url => ({ "kind": linkKind(url), "url": url }));
+}
+
+function linkKind(url) {
+    if (/github\.com|gitlab\.com|codeberg\.org|\.git$/i.test(url)) {
+        return "repo";
+    } else if (/youtube\.com|youtu\.be|vimeo\.com|peertube/i.test(url)) {
+        return "video";
+    } else if (/\.(pdf|pptx?|odp|key)$|slides|docs\.google\.com\/presentation/i.test(url)) {
+        return "slides";
+    }
+    return "other";
+}
+
+// Tags are separated by spaces or commas, the server lowercases them
+function splitTags(tags) {
+    return tags.split(/[\s,]+/).filter(tag => tag.length > 0);
+}
+
+// Co-presenters are separated by commas or ampersands, e.g. "Alice & Bob"
+function splitPresenters(names) {
+    return names.split(/[,&]/).map(name => name.trim()).filter(name => name.length > 0);
+}
+
+function suggestPresenters() {
+    // Only the presenter being typed is looked up, the ones before it are kept
+    let names = document.getElementById("name").value;
+    let split = Math.max(names.lastIndexOf(","), names.lastIndexOf("&")) + 1;
+    let before = names.substring(0, split);
+    let name = names.substring(split).trim();
+
+    fetch("/api/v1/presenters/suggest?name=" + encodeURIComponent(name))
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (presenters) {
+            let list = document.getElementById("presenters");
+            list.innerHTML = "";
+
+            presenters.forEach(presenter => {
+                let option = document.createElement("option");
+                option.value = before + (before ? " " : "") + presenter.name;
+                list.appendChild(option);
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
 async function checkAndReset() {
     // Check if websocket is running
     if (!websocket) {
@@ -124,13 +313,6 @@ async function checkAndReset() {
 // Periodically check that the websocket is open, if not create a new one
 setInterval(checkAndReset, 10000);
 
-var ordering = {}
-ordering["forum topic"] = 1
-ordering["lightning talk"] = 2
-ordering["project update"] = 3
-ordering["announcement"] = 4
-ordering["after meeting slot"] = 5
-
 function register() {
     // Register a websocket connection
     fetch("/register")
@@ -148,10 +330,101 @@ function register() {
             websocket.onmessage = function (event) {
                 let json = JSON.parse(event.data);
 
+                // Every change to the talks moves the agenda, the timer ticks every second
+                if (json.event == "Timer") {
+                    showTimer(json);
+                    return;
+                } else if (json.event == "Overrun") {
+                    document.getElementById("timer").className = "timer overrun";
+                    return;
+                } else if (json.event == "Error") {
+                    // Only this client is told its request was refused
+                    alert(json.error);
+                    return;
+                } else if (json.event != "Authenticate") {
+                    refreshAgenda();
+                }
+
                 if (json.event == "Show") {
+                    removeProposal(json.id);
                     addTalk(json);
-                } else if (json.event == "Hide") {
-                    // Remove the row with matching id
+                } else if (json.event == "Proposed") {
+                    // Edited proposals are sent again
+                    if (admin) {
+                        removeProposal(json.talk.id);
+                        addProposal(json.talk);
+                    } else {
+                        alert("Thanks! Your talk waits for a moderator to approve it.");
+                    }
+                } else if (json.event == "Rejected") {
+                    if (admin) {
+                        removeProposal(json.id);
+                    } else {
+                        alert("Your proposed talk was not accepted." + (json.reason ? " " + json.reason : ""));
+                    }
+                } else if (json.event == "Update") {
+                    // Replace the row with matching id
+                    var rows = document.getElementById('tb').children;
+
+                    for (i = 0; i < rows.length - 1; i++) {
+                        if (json.id == rows[i].children[0].innerHTML) {
+                            rows[i].remove();
+                            addTalk(json);
+                            break;
+                        }
+                    }
+                } else if (json.event == "TalkTypes") {
+                    showTalkTypes(json.talk_types);
+                } else if (json.event == "Vote") {
+                    // Show the new count and move the topic up when topics are sorted by votes
+                    var rows = document.getElementById('tb').children;
+
+                    for (i = 0; i < rows.length - 1; i++) {
+                        if (json.id == rows[i].children[0].innerHTML) {
+                            let count = rows[i].querySelector("span.votes");
+                            if (count) {
+                                count.innerText = json.votes;
+                            }
+                            if (document.getElementById('tb').dataset.sortVotes) {
+                                sortByVotes(rows[i]);
+                            }
+                            break;
+                        }
+                    }
+                } else if (json.event == "MergePresenters") {
+                    // Show the new presenters of the moved talks
+                    var rows = document.getElementById('tb').children;
+
+                    json.talks.forEach(talk => {
+                        for (i = 0; i < rows.length - 1; i++) {
+                            if (talk.id == rows[i].children[0].innerHTML) {
+                                setPresenters(rows[i].children[1], talk);
+                            }
+                        }
+                    });
+                } else if (json.event == "Presenting") {
+                    refreshTimer();
+
+                    // Highlight the current talk and mark the finished ones
+                    var rows = document.getElementById('tb').children;
+
+                    for (i = 0; i < rows.length - 1; i++) {
+                        let id = parseInt(rows[i].children[0].innerHTML);
+                        let presented = json.presented.find(talk => talk.id == id);
+
+                        if (id == json.current) {
+                            rows[i].setAttribute("class", "event current");
+                            rows[i].removeAttribute("title");
+                        } else if (presented) {
+                            rows[i].setAttribute("class", "event presented");
+                            rows[i].setAttribute("title", "Presented at " + presented.presented_at.substring(11, 16));
+                        } else {
+                            rows[i].setAttribute("class", "event");
+                            rows[i].removeAttribute("title");
+                        }
+                    }
+                } else if (json.event == "Hide" || json.event == "Status") {
+                    // Remove the row with matching id, scheduled talks are shown again
                     var rows = document.getElementById('tb').children;
 
                     for (i = 0; i < rows.length - 1; i++) {
@@ -160,6 +433,12 @@ function register() {
                             break;
                         }
                     }
+
+                    if (json.status == "scheduled") {
+                        fetch("/api/v1/talks/" + json.id)
+                            .then(response => response.json())
+                            .then(talk => addTalk(talk));
+                    }
                 }
             }
             wsID = result.id;
@@ -173,15 +452,16 @@ function addTalk(json) {
     var table = document.getElementById('table');
     var rows = document.getElementById('tb').children;
 
+    let talkType = json.type;
+
     // Insert the new data into the correct location in the table
     let i = 0
     for (i = 0; i < rows.length - 1; i++) {
         // Order by talk type then by id
 
-        let order = ordering[rows[i].children[2].innerText];
-        let id = rows[i].children[0].innerText;
+        let order = parseInt(rows[i].dataset.position);
 
-        if (ordering[json.talk_type] < order) {
+        if (talkType.position < order) {
             break;
         }
     }
@@ -189,27 +469,188 @@ function addTalk(json) {
     // Building a new event object using _javascript_
     var row = table.insertRow(i + 1);
     row.setAttribute("class", "event");
+    row.dataset.type = talkType.id;
+    row.dataset.position = talkType.position;
 
     var c0 = row.insertCell(0);
     c0.setAttribute("style", "display: none;");
-    c0.innerHTML = json.id;
+    c0.innerText = json.id;
 
     var c1 = row.insertCell(1);
     c1.setAttribute("class", "name");
-    c1.innerHTML = json.name;
+    setPresenters(c1, json);
 
     var c2 = row.insertCell(2);
     c2.setAttribute("class", "type");
-    c2.innerHTML = json.talk_type;
+    c2.innerText = talkType.label;
+    c2.style.borderLeftColor = talkType.color;
 
     var c3 = row.insertCell(3);
     c3.setAttribute("class", "desc");
-    c3.innerHTML = json.description;
+    // The description is rendered and sanitized by the server, nothing else is inserted as html
+    c3.innerHTML = json.description_html;
+    let link = document.createElement("a");
+    link.setAttribute("class", "link");
+    link.href = "/talks/" + json.id;
+    link.innerText = "details";
+    c3.append(" ", link);
+    (json.links || []).forEach(talkLink => {
+        let anchor = document.createElement("a");
+        anchor.setAttribute("class", "link");
+        anchor.href = talkLink.url;
+        anchor.target = "_blank";
+        anchor.rel = "noopener";
+        anchor.innerText = talkLink.kind == "other" ? "link" : talkLink.kind;
+        c3.append(" ", anchor);
+    });
+    (json.tags || []).forEach(tag => {
+        let anchor = document.createElement("a");
+        anchor.setAttribute("class", "tag");
+        anchor.href = "/tags/" + encodeURIComponent(tag);
+        anchor.innerText = "#" + tag;
+        c3.append(" ", anchor);
+    });
 
     var c4 = row.insertCell(4);
     c4.setAttribute("class", "actions");
-    c4.innerHTML = '<button onclick="hide(' + json.id + ')"> x </button>';
+    c4.innerHTML = '<button onclick="hide(' + json.id + ')"> x </button> <button onclick="withdraw(' + json.id + ')" title="Withdraw this talk"> withdraw </button>';
+    if (talkType.accepts_votes) {
+        c4.innerHTML += ' <button class="vote" onclick="vote(' + json.id + ')" title="Vote to discuss this first"> &#9650; <span class="votes">' + json.votes + '</span> </button>';
+    }
+
+}
+
+// Show the time left for the talk being presented, as counted by the server
+function showTimer(timer) {
+    let element = document.getElementById("timer");
+
+    if (timer.talk == null) {
+        element.innerText = "--:--";
+        element.className = "timer";
+        return;
+    }
+
+    let seconds = Math.abs(timer.remaining);
+    let text = Math.floor(seconds / 60) + ":" + String(seconds % 60).padStart(2, "0");
+
+    element.innerText = (timer.remaining < 0 ? "-" + text : text) + (timer.running ? "" : " (paused)");
+    element.className = timer.remaining < 0 ? "timer overrun" : "timer";
+}
 
+function refreshTimer() {
+    fetch("/api/v1/timer")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(showTimer)
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Move a topic above the topics of the same type with fewer votes
+function sortByVotes(row) {
+    let votes = r => parseInt(r.querySelector("span.votes").innerText);
+    let type = row.dataset.type;
+
+    let above = row.previousElementSibling;
+    while (above && above.dataset.type == type && votes(above) < votes(row)) {
+        above = above.previousElementSibling;
+    }
+
+    if (above != row.previousElementSibling) {
+        above ? above.after(row) : row.parentNode.prepend(row);
+    }
+}
+
+// Show renamed or recolored talk types and offer every type for new talks
+function showTalkTypes(talkTypes) {
+    let select = document.getElementById("type");
+    let selected = select.value;
+    select.innerHTML = "";
+
+    talkTypes.forEach(talkType => {
+        let option = document.createElement("option");
+        option.value = talkType.slug;
+        option.innerText = talkType.label;
+        select.appendChild(option);
+    });
+    if (talkTypes.some(talkType => talkType.slug == selected)) {
+        select.value = selected;
+    }
+
+    let rows = document.getElementById('tb').children;
+    for (i = 0; i < rows.length - 1; i++) {
+        let talkType = talkTypes.find(talkType => talkType.id == rows[i].dataset.type);
+        if (talkType) {
+            rows[i].dataset.position = talkType.position;
+            rows[i].children[2].innerText = talkType.label;
+            rows[i].children[2].style.borderLeftColor = talkType.color;
+        }
+    }
+}
+
+// Reload the timed agenda below the talks
+function refreshAgenda() {
+    fetch("/api/v1/agenda")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (agenda) {
+            let summary = document.getElementById("agenda-summary");
+            let time = t => t.substring(0, 5);
+
+            if (agenda.overrun > 0) {
+                summary.className = "overrun";
+                summary.innerText = "Runs " + agenda.overrun + " minutes over, projected to end at " + time(agenda.projected_end) + " instead of " + time(agenda.scheduled_end);
+            } else {
+                summary.className = "";
+                summary.innerText = "Projected to end at " + time(agenda.projected_end) + ", " + agenda.minutes + " of " + agenda.meeting_length + " minutes";
+            }
+
+            let rows = document.getElementById("agenda-rows");
+            rows.innerHTML = "";
+
+            agenda.items.forEach(item => {
+                let row = rows.insertRow();
+                row.setAttribute("class", "event");
+
+                [time(item.start), item.talk.name, item.talk.type.label, item.minutes].forEach((value, i) => {
+                    let cell = row.insertCell();
+                    cell.innerText = value;
+                    if (i == 1) {
+                        cell.setAttribute("class", "name");
+                    } else if (i == 2) {
+                        cell.setAttribute("class", "type");
+                        cell.style.borderLeftColor = item.talk.type.color;
+                    }
+                });
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Fill a cell with links to every presenter of a talk
+function setPresenters(cell, talk) {
+    cell.innerHTML = "";
+
+    if (!talk.presenters || talk.presenters.length == 0) {
+        cell.innerText = talk.name;
+        return;
+    }
+
+    talk.presenters.forEach((presenter, i) => {
+        if (i > 0) {
+            cell.appendChild(document.createTextNode(i == talk.presenters.length - 1 ? " & " : ", "));
+        }
+
+        let link = document.createElement("a");
+        link.href = "/presenters/" + presenter.id;
+        link.innerText = presenter.name;
+        cell.appendChild(link);
+    });
 }
 
 // Setup the nav bar
@@ -0,0 +1,218 @@
+var websocket = null;
+var clientID = null;
+var authenticated = false;
+
+window.onload = function () {
+    connect();
+};
+
+// Watch the talk so new comments arrive live
+function connect() {
+    fetch("/register")
+        .then(function (response) {
+            return response.json();
+        })
+        .then(function (result) {
+            clientID = result.id;
+            authenticated = result.authenticated;
+
+            let protocol = window.location.protocol[4] == 's' ? "wss://" : "ws://";
+            websocket = new WebSocket(protocol + window.location.host + "/ws/" + result.id);
+
+            websocket.onopen = function () {
+                websocket.send(JSON.stringify({ "event": "Watch", "id": talkID() }));
+            };
+
+            websocket.onmessage = function (event) {
+                let json = JSON.parse(event.data);
+
+                if (json.event == "Comment" && json.talk_id == talkID()) {
+                    addComment(json);
+                } else if (json.event == "Attachment" && json.talk_id == talkID()) {
+                    addAttachment(json);
+                } else if (json.event == "HideComment") {
+                    let comment = document.getElementById("comment-" + json.id);
+                    if (comment) {
+                        comment.remove();
+                    }
+                }
+            };
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Reconnect when the websocket closes
+setInterval(function () {
+    if (websocket && websocket.readyState != WebSocket.OPEN && websocket.readyState != WebSocket.CONNECTING) {
+        connect();
+    }
+}, 10000);
+
+function talkID() {
+    return parseInt(document.body.dataset.talk);
+}
+
+function addComment(comment) {
+    if (document.getElementById("comment-" + comment.id)) {
+        return;
+    }
+
+    let div = document.createElement("div");
+    div.setAttribute("class", "comment");
+    div.setAttribute("id", "comment-" + comment.id);
+
+    let heading = document.createElement("h5");
+    heading.innerText = comment.author + " · " + comment.created_at.substring(0, 16).replace("T", " ") + " ";
+
+    let hide = document.createElement("button");
+    hide.innerText = "x";
+    hide.onclick = function () {
+        hideComment(comment.id);
+    };
+    heading.appendChild(hide);
+
+    let body = document.createElement("p");
+    body.innerText = comment.body;
+
+    div.appendChild(heading);
+    div.appendChild(body);
+    document.getElementById("comments").appendChild(div);
+}
+
+function addAttachment(attachment) {
+    if (document.getElementById("attachment-" + attachment.id)) {
+        return;
+    }
+
+    let item = document.createElement("li");
+    item.setAttribute("id", "attachment-" + attachment.id);
+
+    let link = document.createElement("a");
+    link.setAttribute("href", "/attachments/" + attachment.id);
+    link.innerText = attachment.file_name;
+
+    item.appendChild(link);
+    item.appendChild(document.createTextNode(" (" + Math.floor(attachment.size / 1024) + " KB)"));
+    document.getElementById("attachments").appendChild(item);
+}
+
+// Asks for a password and authenticates the client with it
+function login(message) {
+    let password = prompt(message);
+    if (!password) {
+        return Promise.resolve(null);
+    }
+
+    return fetch("/authenticate", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ "id": clientID, "password": password }),
+    }).then(function (response) {
+        return response.json();
+    });
+}
+
+function comment() {
+    let author = document.getElementById("author");
+    let body = document.getElementById("body");
+
+    if (!clientID || !author.value.trim() || !body.value.trim()) {
+        return;
+    }
+
+    let ready = authenticated ? Promise.resolve() : login("Please enter the password.").then(function (result) {
+        authenticated = result != null && result.authenticated;
+    });
+
+    ready
+        .then(function () {
+            if (!authenticated) {
+                return;
+            }
+
+            return fetch("/api/v1/talks/" + talkID() + "/comments", {
+                method: "POST",
+                headers: { "Content-Type": "application/json", "x-client-id": clientID },
+                body: JSON.stringify({ "author": author.value, "body": body.value }),
+            }).then(function (response) {
+                return response.json().then(function (result) {
+                    if (response.ok) {
+                        body.value = "";
+                        addComment(result);
+                    } else {
+                        alert(result.error);
+                    }
+                });
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Only admins may hide comments, the admin password is needed every time
+function hideComment(id) {
+    login("Please enter the admin password.")
+        .then(function (result) {
+            if (!result) {
+                return;
+            }
+
+            return fetch("/api/v1/comments/" + id, {
+                method: "DELETE",
+                headers: { "x-client-id": clientID },
+            }).then(function (response) {
+                if (!response.ok) {
+                    return response.json().then(function (result) {
+                        alert(result.error);
+                    });
+                }
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}
+
+// Slides are uploaded as a multipart form, the server checks the size and type
+function upload() {
+    let file = document.getElementById("file");
+
+    if (!clientID || file.files.length == 0) {
+        return;
+    }
+
+    let ready = authenticated ? Promise.resolve() : login("Please enter the password.").then(function (result) {
+        authenticated = result != null && result.authenticated;
+    });
+
+    ready
+        .then(function () {
+            if (!authenticated) {
+                return;
+            }
+
+            let form = new FormData();
+            form.append("file", file.files[0]);
+
+            return fetch("/api/v1/talks/" + talkID() + "/attachments", {
+                method: "POST",
+                headers: { "x-client-id": clientID },
+                body: form,
+            }).then(function (response) {
+                return response.json().then(function (result) {
+                    if (response.ok) {
+                        file.value = "";
+                        addAttachment(result);
+                    } else {
+                        alert(result.error);
+                    }
+                });
+            });
+        })
+        .catch(function (error) {
+            console.log("Error: " + error);
+        });
+}